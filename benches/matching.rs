@@ -0,0 +1,67 @@
+// Run with `cargo bench`. There is no benchmark harness dependency, so this times each case
+// with `Instant` over a fixed number of iterations and prints the mean per iteration.
+use geocoder_abbreviations::{abbreviate, abbreviate_batch, config_one, supported_languages, IndexedMatcher, Tokenizer};
+use std::time::{Duration, Instant};
+
+const CORPUS: &[&str] = &[
+    "123 North Main Street Apartment 4",
+    "Fort Worth Avenue",
+    "1st Avenue Northwest",
+    "Saint Mary's Road",
+    "45 Martin Luther King Junior Boulevard Suite 200",
+    "Post Office Box 12",
+    "Old Country Road East",
+    "Streetsboro",
+    "Mount Pleasant Parkway South",
+    "Northwest Highway Service Road",
+];
+
+fn time<F: FnMut()>(iterations: u32, mut f: F) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    println!("config cold load");
+    for lc in supported_languages() {
+        let mean = time(20, || {
+            config_one(&lc).unwrap();
+        });
+        println!("  {:<4} {:>10.2?}", lc, mean);
+    }
+
+    let en = config_one("en").unwrap();
+    let linear = Tokenizer::new(&en);
+    let indexed = Tokenizer::new(&en).with_matcher(IndexedMatcher::default());
+    let run = |tokenize: &dyn Fn(&str) -> String| {
+        for input in CORPUS {
+            tokenize(input);
+        }
+    };
+    let linear_mean = time(200, || run(&|input| linear.tokenize(input)));
+    let indexed_mean = time(200, || run(&|input| indexed.tokenize(input)));
+
+    println!("abbreviate en corpus ({} addresses)", CORPUS.len());
+    println!("  linear scan {:>10.2?}", linear_mean);
+    println!("  indexed     {:>10.2?}", indexed_mean);
+    println!("  speedup     {:>9.1}x", linear_mean.as_secs_f64() / indexed_mean.as_secs_f64());
+
+    let inputs: Vec<String> = CORPUS.iter().cycle().take(1000).map(|input| input.to_string()).collect();
+    let naive_mean = time(5, || {
+        for input in &inputs {
+            abbreviate(input, &en);
+        }
+    });
+    let batch_mean = time(5, || {
+        abbreviate_batch(&inputs, &en);
+    });
+
+    println!("abbreviate {} addresses", inputs.len());
+    println!("  one call each {:>10.2?}", naive_mean);
+    println!("  batch         {:>10.2?}", batch_mean);
+    println!("  speedup       {:>9.1}x", naive_mean.as_secs_f64() / batch_mean.as_secs_f64());
+}
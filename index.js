@@ -38,7 +38,13 @@ function config(lang, singletons, advanced) {
     const tokens = {};
 
     fs.readdirSync(path.resolve(__dirname, './tokens/')).forEach((token) => {
-        if (token.match(/\.json$/)) {
+        if (token === 'global.json') {
+            // language-independent groups for the Rust crate; `global` is the global.js regex map
+            return;
+        } else if (token.match(/^[a-z]+\.[A-Z]{2}\.json$/)) {
+            // `<lc>.<CC>.json` country overlays for the Rust crate's `config_for_country`
+            return;
+        } else if (token.match(/\.json$/)) {
             let json = require(`./tokens/${token}`);
 
             tokens[token.replace(/\.json/, '')] = prepare(json, singletons, advanced);
@@ -0,0 +1,226 @@
+use crate::{Token, TokenType};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct AmbiguityReport {
+    /// Canonical forms shared by groups with different full forms, e.g. "Cl" for both
+    /// "Clinic" and "Close". These can't be expanded unambiguously.
+    pub ambiguous_canonicals: usize,
+    /// Full forms shared by groups with different canonical forms. These can't be
+    /// abbreviated unambiguously.
+    pub ambiguous_fulls: usize,
+}
+
+/// The writing systems used by the built-in token files.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Hebrew,
+    Sinhala,
+    Han,
+    Hiragana,
+    Katakana,
+    Hangul,
+    /// Text with no letters from any of the scripts above, e.g. only digits.
+    Other,
+}
+
+impl Script {
+    fn of(c: char) -> Option<Script> {
+        match c {
+            'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}' => Some(Script::Latin),
+            '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Some(Script::Greek),
+            '\u{0400}'..='\u{052F}' => Some(Script::Cyrillic),
+            '\u{0590}'..='\u{05FF}' => Some(Script::Hebrew),
+            '\u{0D80}'..='\u{0DFF}' => Some(Script::Sinhala),
+            '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7AF}' => Some(Script::Hangul),
+            '\u{3040}'..='\u{309F}' => Some(Script::Hiragana),
+            '\u{30A0}'..='\u{30FF}' => Some(Script::Katakana),
+            '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}' => Some(Script::Han),
+            _ => None
+        }
+    }
+}
+
+/// Groups `tokens` by the script most of the letters of their `full` form are written in.
+/// Ties go to the script that appears first.
+pub fn tokens_by_script(tokens: &[Token]) -> HashMap<Script, Vec<&Token>> {
+    let mut scripts: HashMap<Script, Vec<&Token>> = HashMap::new();
+    for tk in tokens {
+        let mut counts: Vec<(Script, usize)> = Vec::new();
+        for script in tk.full.as_str().chars().filter_map(Script::of) {
+            match counts.iter_mut().find(|(s, _)| *s == script) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((script, 1))
+            }
+        }
+        let mut dominant = Script::Other;
+        let mut most = 0;
+        for (script, count) in counts {
+            if count > most {
+                dominant = script;
+                most = count;
+            }
+        }
+        scripts.entry(dominant).or_default().push(tk);
+    }
+    scripts
+}
+
+/// Counts the forms in `tokens` that don't map back to a single counterpart. Forms are
+/// compared case-insensitively; regex tokens are left out since their full form is a pattern.
+pub fn ambiguity_report(tokens: &[Token]) -> AmbiguityReport {
+    let mut fulls_by_canonical: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut canonicals_by_full: HashMap<String, HashSet<String>> = HashMap::new();
+    for tk in tokens.iter().filter(|tk| !tk.regex) {
+        let full = tk.full.as_str().to_lowercase();
+        let canonical = tk.canonical.to_lowercase();
+        fulls_by_canonical.entry(canonical.clone()).or_default().insert(full.clone());
+        canonicals_by_full.entry(full).or_default().insert(canonical);
+    }
+    AmbiguityReport {
+        ambiguous_canonicals: fulls_by_canonical.values().filter(|fulls| fulls.len() > 1).count(),
+        ambiguous_fulls: canonicals_by_full.values().filter(|canonicals| canonicals.len() > 1).count(),
+    }
+}
+
+/// Lists the `(shorter, longer)` pairs of literal token forms where the shorter one occurs
+/// inside the longer one, e.g. `("Ave", "Avenue")`, sorted and without duplicates.
+pub fn substring_hazards(tokens: &[Token]) -> Vec<(String, String)> {
+    let forms: BTreeSet<&str> = tokens.iter()
+        .filter(|tk| !tk.regex)
+        .flat_map(|tk| tk.tokens.iter().map(String::as_str))
+        .filter(|form| !form.is_empty())
+        .collect();
+    let mut hazards = Vec::new();
+    for short in &forms {
+        for long in &forms {
+            if long.len() > short.len() && long.contains(short) {
+                hazards.push((short.to_string(), long.to_string()));
+            }
+        }
+    }
+    hazards
+}
+
+/// A rough estimate of how many bytes abbreviating one match adds to the text, negative when
+/// it shrinks: the mean of `canonical.len() - full.len()` over the literal tokens.
+pub fn estimate_length_delta(tokens: &[Token]) -> i64 {
+    let deltas: Vec<i64> = tokens.iter()
+        .filter(|tk| !tk.regex)
+        .map(|tk| tk.canonical.len() as i64 - tk.full.as_str().len() as i64)
+        .collect();
+    if deltas.is_empty() {
+        return 0;
+    }
+    deltas.iter().sum::<i64>() / deltas.len() as i64
+}
+
+/// Counts the tokens of every language in `config` by type, with untyped tokens under `None`.
+pub fn type_histogram(config: &HashMap<String, Vec<Token>>) -> HashMap<Option<TokenType>, usize> {
+    let mut histogram = HashMap::new();
+    for tk in config.values().flatten() {
+        *histogram.entry(tk.token_type).or_insert(0) += 1;
+    }
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::test_util::token;
+
+    #[test]
+    fn test_ambiguity_report() {
+        let tokens = vec![
+            token(r#"{ "tokens": ["Cl", "Clinic"], "full": "Clinic", "canonical": "Cl" }"#),
+            token(r#"{ "tokens": ["Cl", "Close"], "full": "Close", "canonical": "Cl" }"#),
+            token(r#"{ "tokens": ["Ct", "Court"], "full": "Court", "canonical": "Ct" }"#),
+            token(r#"{ "tokens": ["Crt", "Court"], "full": "court", "canonical": "Crt" }"#),
+            token(r#"{ "tokens": ["St", "Street"], "full": "Street", "canonical": "St" }"#),
+        ];
+        assert_eq!(ambiguity_report(&tokens), AmbiguityReport {
+            ambiguous_canonicals: 1,
+            ambiguous_fulls: 1,
+        });
+
+        let map = config(vec![String::from("en")]).unwrap();
+        let report = ambiguity_report(&map["en"]);
+        assert!(report.ambiguous_canonicals > 0);
+        assert!(report.ambiguous_canonicals < map["en"].len());
+        assert!(report.ambiguous_fulls < map["en"].len());
+    }
+
+    #[test]
+    fn test_substring_hazards() {
+        let tokens = vec![
+            token(r#"{ "tokens": ["Ave", "Avenue"], "full": "Avenue", "canonical": "Ave" }"#),
+            token(r#"{ "tokens": ["St", "Street"], "full": "Street", "canonical": "St" }"#),
+            token(r#"{ "tokens": ["Rd", "Road"], "full": "Road", "canonical": "Rd" }"#),
+            token(r#"{ "tokens": ["", "St [0-9]+"], "full": "St [0-9]+", "canonical": "", "regex": true }"#),
+        ];
+        assert_eq!(substring_hazards(&tokens), vec![
+            (String::from("Ave"), String::from("Avenue")),
+            (String::from("St"), String::from("Street")),
+        ]);
+    }
+
+    #[test]
+    fn test_estimate_length_delta() {
+        let tokens = vec![
+            token(r#"{ "tokens": ["St", "Street"], "full": "Street", "canonical": "St" }"#),
+            token(r#"{ "tokens": ["Rd", "Road"], "full": "Road", "canonical": "Rd" }"#),
+            token(r#"{ "tokens": ["", "a+"], "full": "a+", "canonical": "aaaaaaaa", "regex": true }"#),
+        ];
+        assert_eq!(estimate_length_delta(&tokens), -3);
+        assert_eq!(estimate_length_delta(&[]), 0);
+
+        let map = config(vec![String::from("en")]).unwrap();
+        assert!(estimate_length_delta(&map["en"]) < 0);
+    }
+
+    #[test]
+    fn test_type_histogram() {
+        let mut config = HashMap::new();
+        config.insert(String::from("aa"), vec![
+            token(r#"{ "tokens": ["St", "Street"], "full": "Street", "canonical": "St", "type": "way" }"#),
+            token(r#"{ "tokens": ["N", "North"], "full": "North", "canonical": "N", "type": "cardinal" }"#),
+            token(r#"{ "tokens": ["Mt", "Mount"], "full": "Mount", "canonical": "Mt" }"#),
+        ]);
+        config.insert(String::from("bb"), vec![
+            token(r#"{ "tokens": ["Rd", "Road"], "full": "Road", "canonical": "Rd", "type": "way" }"#),
+            token(r#"{ "tokens": ["Ste", "Suite"], "full": "Suite", "canonical": "Ste" }"#),
+        ]);
+
+        let histogram = type_histogram(&config);
+        assert_eq!(histogram.len(), 3);
+        assert_eq!(histogram[&Some(TokenType::Way)], 2);
+        assert_eq!(histogram[&Some(TokenType::Cardinal)], 1);
+        assert_eq!(histogram[&None], 2);
+    }
+
+    #[test]
+    fn test_tokens_by_script() {
+        let tokens = vec![
+            token(r#"{ "tokens": ["bul", "bulevar"], "full": "bulevar", "canonical": "bul" }"#),
+            token(r#"{ "tokens": ["бул", "булевар"], "full": "булевар", "canonical": "бул" }"#),
+            token(r#"{ "tokens": ["ул", "улица"], "full": "улица", "canonical": "ул" }"#),
+            token(r#"{ "tokens": ["1", "10"], "full": "10", "canonical": "1" }"#),
+        ];
+        let scripts = tokens_by_script(&tokens);
+        let fulls = |script| -> Vec<&str> { scripts[&script].iter().map(|tk| tk.full.as_str()).collect() };
+
+        assert_eq!(scripts.len(), 3);
+        assert_eq!(fulls(Script::Latin), vec!["bulevar"]);
+        assert_eq!(fulls(Script::Cyrillic), vec!["булевар", "улица"]);
+        assert_eq!(fulls(Script::Other), vec!["10"]);
+
+        let map = config(vec![String::from("sr")]).unwrap();
+        let scripts = tokens_by_script(&map["sr"]);
+        assert!(scripts.contains_key(&Script::Latin));
+        assert!(scripts.contains_key(&Script::Cyrillic));
+    }
+}
@@ -0,0 +1,222 @@
+//! A compact, length-prefixed binary encoding of a prepared config, for services that want
+//! faster cold starts than parsing the token JSON. Regexes are stored as their patterns and
+//! recompiled on load, so a decoded config behaves exactly like the one that was encoded.
+//!
+//! Layout: the magic bytes `GCA2`, whose last byte is the format version, then the language
+//! count and each language (sorted by code) as its code followed by its tokens. Integers are
+//! little-endian `u32`s, strings are a length followed by UTF-8 bytes, and optional values
+//! are prefixed with a 0/1 tag.
+//!
+//! This stands in for a `bincode` encoding (`to_bincode`/`from_bincode` behind a `bincode`
+//! feature), which would need a dependency the crate can't take on. The format is its own,
+//! so the functions and feature are named after it instead.
+
+use crate::{Error, InToken, Token};
+use std::collections::HashMap;
+
+const MAGIC: &[u8] = b"GCA2";
+
+pub fn to_binary(config: &HashMap<String, Vec<Token>>) -> Vec<u8> {
+    let mut out = Vec::from(MAGIC);
+    let mut lcs: Vec<&String> = config.keys().collect();
+    lcs.sort();
+    put_u32(&mut out, lcs.len());
+    for lc in lcs {
+        put_str(&mut out, lc);
+        put_u32(&mut out, config[lc].len());
+        for tk in &config[lc] {
+            put_token(&mut out, &tk.to_input());
+        }
+    }
+    out
+}
+
+pub fn from_binary(bytes: &[u8]) -> Result<HashMap<String, Vec<Token>>, Error> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let magic = reader.take(MAGIC.len())?;
+    if magic[..3] != MAGIC[..3] {
+        return Err(Error::BinaryDecodeError(String::from("missing GCA header")));
+    }
+    if magic != MAGIC {
+        return Err(Error::BinaryDecodeError(format!("unsupported format version {}", magic[3] as char)));
+    }
+    let mut config = HashMap::new();
+    for _ in 0..reader.u32()? {
+        let lc = reader.string()?;
+        let count = reader.u32()?;
+        let mut tokens = Vec::with_capacity(count.min(bytes.len()));
+        for _ in 0..count {
+            tokens.push(Token::load(reader.token()?, &lc)?);
+        }
+        config.insert(lc, tokens);
+    }
+    if reader.pos != bytes.len() {
+        return Err(Error::BinaryDecodeError(String::from("trailing bytes after config")));
+    }
+    Ok(config)
+}
+
+fn put_u32(out: &mut Vec<u8>, n: usize) {
+    out.extend_from_slice(&(n as u32).to_le_bytes());
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) {
+    put_u32(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn put_strs(out: &mut Vec<u8>, v: &[String]) {
+    put_u32(out, v.len());
+    for s in v {
+        put_str(out, s);
+    }
+}
+
+fn put_opt<T>(out: &mut Vec<u8>, value: &Option<T>, put: impl Fn(&mut Vec<u8>, &T)) {
+    match value {
+        Some(value) => {
+            out.push(1);
+            put(out, value);
+        },
+        None => out.push(0)
+    }
+}
+
+fn put_token(out: &mut Vec<u8>, tk: &InToken) {
+    put_strs(out, &tk.tokens);
+    put_str(out, &tk.full);
+    put_str(out, &tk.canonical);
+    put_opt(out, &tk.note, |out, s| put_str(out, s));
+    put_opt(out, &tk.only_countries, |out, v| put_strs(out, v));
+    put_opt(out, &tk.only_layers, |out, v| put_strs(out, v));
+    let flags = [tk.prefer_full, tk.regex, tk.skip_boundaries, tk.skip_diacritic_stripping, tk.requires_number, tk.case_insensitive]
+        .iter()
+        .enumerate()
+        .fold(0u8, |flags, (i, flag)| flags | ((flag.unwrap_or(false) as u8) << i));
+    out.push(flags);
+    put_opt(out, &tk.span_boundaries, |out, n| out.push(*n));
+    put_opt(out, &tk.token_type, |out, s| put_str(out, s));
+    put_opt(out, &tk.min_length, |out, n| put_u32(out, *n));
+    put_opt(out, &tk.max_length, |out, n| put_u32(out, *n));
+    put_opt(out, &tk.ascii_canonical, |out, s| put_str(out, s));
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, n: usize) -> Result<&'b [u8], Error> {
+        let end = self.pos.checked_add(n).filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| Error::BinaryDecodeError(String::from("unexpected end of input")))?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<usize, Error> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf) as usize)
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let len = self.u32()?;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| Error::BinaryDecodeError(String::from("invalid UTF-8 in string")))
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>, Error> {
+        (0..self.u32()?).map(|_| self.string()).collect()
+    }
+
+    fn opt<T>(&mut self, read: impl Fn(&mut Self) -> Result<T, Error>) -> Result<Option<T>, Error> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(read(self)?)),
+            tag => Err(Error::BinaryDecodeError(format!("invalid option tag {}", tag)))
+        }
+    }
+
+    fn token(&mut self) -> Result<InToken, Error> {
+        let tokens = self.strings()?;
+        let full = self.string()?;
+        let canonical = self.string()?;
+        let note = self.opt(Self::string)?;
+        let only_countries = self.opt(Self::strings)?;
+        let only_layers = self.opt(Self::strings)?;
+        let flags = self.u8()?;
+        let flag = |i: u8| Some(flags & (1 << i) != 0).filter(|set| *set);
+        Ok(InToken {
+            tokens,
+            full,
+            canonical,
+            note,
+            only_countries,
+            only_layers,
+            prefer_full: flag(0),
+            regex: flag(1),
+            skip_boundaries: flag(2),
+            skip_diacritic_stripping: flag(3),
+            requires_number: flag(4),
+            span_boundaries: self.opt(Self::u8)?,
+            token_type: self.opt(Self::string)?,
+            min_length: self.opt(Self::u32)?,
+            max_length: self.opt(Self::u32)?,
+            ascii_canonical: self.opt(Self::string)?,
+            case_insensitive: flag(5),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{abbreviate, config, config_with, ConfigOptions};
+
+    #[test]
+    fn test_binary_round_trip() {
+        let original = config(Vec::new()).unwrap();
+        let bytes = to_binary(&original);
+        assert_eq!(from_binary(&bytes).unwrap(), original);
+        assert_eq!(to_binary(&from_binary(&bytes).unwrap()), bytes);
+    }
+
+    #[test]
+    fn test_binary_case_insensitive() {
+        let options = ConfigOptions::new().languages(vec![String::from("en")]).case_insensitive(true).build();
+        let original = config_with(options).unwrap();
+        let decoded = from_binary(&to_binary(&original)).unwrap();
+        assert_eq!(decoded, original);
+        assert!(decoded["en"].iter().all(|tk| tk.case_insensitive));
+        assert_eq!(abbreviate("Main AVE", &decoded["en"]), "Main Av");
+    }
+
+    #[test]
+    fn test_binary_errors() {
+        let bytes = to_binary(&config(vec![String::from("de")]).unwrap());
+        assert!(from_binary(b"nope").is_err());
+        assert!(from_binary(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(from_binary(&trailing).is_err());
+        assert_eq!(from_binary(&trailing).unwrap_err().to_string(), "unable to decode binary config: trailing bytes after config");
+    }
+
+    #[test]
+    fn test_binary_version() {
+        let bytes = to_binary(&config(vec![String::from("de")]).unwrap());
+        assert_eq!(&bytes[..4], b"GCA2");
+
+        let mut old = bytes.clone();
+        old[3] = b'1';
+        assert_eq!(from_binary(&old), Err(Error::BinaryDecodeError(String::from("unsupported format version 1"))));
+        assert_eq!(from_binary(b"GCB2"), Err(Error::BinaryDecodeError(String::from("missing GCA header"))));
+    }
+}
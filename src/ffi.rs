@@ -0,0 +1,180 @@
+//! A C interface to the tokenizer, for services that link against this crate instead of
+//! reimplementing the token logic. Build it as a C library with
+//! `cargo rustc --release --features ffi --crate-type cdylib` (or `staticlib`). The
+//! declarations below match what `cbindgen --lang c` generates for this module:
+//!
+//! ```c
+//! typedef struct Handle Handle;
+//!
+//! Handle *gca_config(const char *langs);
+//! char *gca_abbreviate(const Handle *handle, const char *input);
+//! char *gca_last_error(void);
+//! void gca_free_string(char *s);
+//! void gca_free_handle(Handle *handle);
+//! ```
+//!
+//! No function panics across the boundary. Functions that fail return null, after which
+//! `gca_last_error` describes the failure.
+
+use crate::{config, merge, Token, Tokenizer};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+/// The tokens of one `gca_config` call, merged in the order the languages were given, and
+/// a tokenizer over them built once for every `gca_abbreviate` call.
+pub struct Handle {
+    // Borrows `tokens`, which is why it is declared first: fields drop in order, so it never
+    // outlives them.
+    tokenizer: Tokenizer<'static>,
+    // Only held for the tokenizer, and never modified after it is built, so the tokens it
+    // points at stay put even when the handle moves.
+    _tokens: Vec<Token>,
+}
+
+impl Handle {
+    fn new(tokens: Vec<Token>) -> Self {
+        // SAFETY: the slice lives in `tokens`' heap buffer, which the handle owns, never
+        // changes and frees only after dropping `tokenizer`.
+        let slice: &'static [Token] = unsafe { &*(tokens.as_slice() as *const [Token]) };
+        Handle {
+            tokenizer: Tokenizer::with_token_languages(slice),
+            _tokens: tokens,
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn fail<T>(message: String) -> *mut T {
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    ptr::null_mut()
+}
+
+// Runs `f`, turning an error or a panic into a null return and a recorded message.
+fn guard<T>(f: impl FnOnce() -> Result<*mut T, String>) -> *mut T {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(out)) => out,
+        Ok(Err(message)) => fail(message),
+        Err(_) => fail(String::from("panicked"))
+    }
+}
+
+unsafe fn read_str<'a>(s: *const c_char, name: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("{} is null", name));
+    }
+    CStr::from_ptr(s).to_str().map_err(|_| format!("{} is not valid UTF-8", name))
+}
+
+/// Loads the comma-separated language codes in `langs`, e.g. "en,de"; an empty string loads
+/// every language. Returns null if a code isn't supported.
+///
+/// # Safety
+///
+/// `langs` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn gca_config(langs: *const c_char) -> *mut Handle {
+    guard(|| {
+        let langs = read_str(langs, "langs")?;
+        let order: Vec<String> = langs.split(',').map(str::trim).filter(|lc| !lc.is_empty()).map(String::from).collect();
+        let map = config(order.clone()).map_err(|e| e.to_string())?;
+        Ok(Box::into_raw(Box::new(Handle::new(merge(map, &order)))))
+    })
+}
+
+/// Abbreviates `input` with the handle's tokens. The result must be released with
+/// `gca_free_string`. Returns null if either argument is null or `input` isn't UTF-8.
+///
+/// # Safety
+///
+/// `handle` must be null or come from `gca_config` and not have been freed; `input` must be
+/// null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn gca_abbreviate(handle: *const Handle, input: *const c_char) -> *mut c_char {
+    guard(|| {
+        let handle = handle.as_ref().ok_or_else(|| String::from("handle is null"))?;
+        let input = read_str(input, "input")?;
+        let out = handle.tokenizer.tokenize(input);
+        CString::new(out).map(CString::into_raw).map_err(|_| String::from("output contains a NUL byte"))
+    })
+}
+
+/// Returns a copy of the message of the last failed call on this thread, or null if none
+/// failed. The result must be released with `gca_free_string`.
+#[no_mangle]
+pub extern "C" fn gca_last_error() -> *mut c_char {
+    LAST_ERROR.with(|last| last.borrow().clone().map_or(ptr::null_mut(), CString::into_raw))
+}
+
+/// Releases a string returned by this library. Null is ignored.
+///
+/// # Safety
+///
+/// `s` must be null or come from this library and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gca_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Releases a handle from `gca_config`. Null is ignored.
+///
+/// # Safety
+///
+/// `handle` must be null or come from `gca_config` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gca_free_handle(handle: *mut Handle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn take(s: *mut c_char) -> Option<String> {
+        if s.is_null() {
+            return None;
+        }
+        let out = CStr::from_ptr(s).to_str().unwrap().to_string();
+        gca_free_string(s);
+        Some(out)
+    }
+
+    #[test]
+    fn test_ffi() {
+        unsafe {
+            let langs = CString::new("en, de").unwrap();
+            let handle = gca_config(langs.as_ptr());
+            assert!(!handle.is_null());
+
+            let input = CString::new("Main Street").unwrap();
+            assert_eq!(take(gca_abbreviate(handle, input.as_ptr())), Some(String::from("Main St")));
+            let input = CString::new("Berliner Straße").unwrap();
+            assert_eq!(take(gca_abbreviate(handle, input.as_ptr())), Some(String::from("Berliner Str")));
+            let input = CString::new("Berliner Strasse").unwrap();
+            assert_eq!(take(gca_abbreviate(handle, input.as_ptr())), Some(String::from("Berliner Str")));
+
+            assert!(gca_abbreviate(handle, ptr::null()).is_null());
+            assert_eq!(take(gca_last_error()), Some(String::from("input is null")));
+            gca_free_handle(handle);
+
+            let langs = CString::new("en,zz").unwrap();
+            assert!(gca_config(langs.as_ptr()).is_null());
+            assert_eq!(take(gca_last_error()), Some(String::from("language code not supported: zz")));
+            assert!(gca_abbreviate(ptr::null(), ptr::null()).is_null());
+            assert_eq!(take(gca_last_error()), Some(String::from("handle is null")));
+
+            gca_free_string(ptr::null_mut());
+            gca_free_handle(ptr::null_mut());
+        }
+    }
+}
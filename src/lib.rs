@@ -1,17 +1,104 @@
+#[cfg(feature = "embedded-tokens")]
 use rust_embed::RustEmbed;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
 use fancy_regex::Regex;
 
+mod analysis;
+#[cfg(feature = "binary")]
+mod binary;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod matcher;
+mod normalize;
+mod numbers;
+mod reload;
+#[cfg(feature = "cjk")]
+mod segment;
+#[cfg(test)]
+mod test_util;
+mod tokenizer;
+mod validate;
+pub use analysis::{
+    ambiguity_report, estimate_length_delta, substring_hazards, tokens_by_script, type_histogram, AmbiguityReport,
+    Script,
+};
+#[cfg(feature = "binary")]
+pub use binary::{from_binary, to_binary};
+pub use matcher::{ExactMatcher, IndexedMatcher, MatchContext, Matcher, Prepared};
+#[cfg(feature = "fuzzy")]
+pub use matcher::FuzzyMatcher;
+pub use normalize::{normalize, strip_diacritics, NormalizeOptions};
+pub use numbers::{normalize_number_ranges, normalize_numerals, words_to_number};
+pub use reload::ReloadableConfig;
+#[cfg(feature = "cjk")]
+pub use segment::{DictionarySegmenter, Segmenter};
+#[cfg(feature = "parallel")]
+pub use tokenizer::abbreviate_batch_par;
+pub use tokenizer::{
+    abbreviate, abbreviate_batch, abbreviate_csv_column, abbreviate_with_spans,
+    changing_languages, expand, expand_all, extract_typed, normalize_cardinals, variants, Decision, Diagnostics,
+    MatchOptions, Rejection, Replacement, TokenMatch, Tokenizer, TokenizerDebug, MAX_EXPANSIONS,
+};
+pub use validate::{validate, validation_warnings};
+
+#[cfg(feature = "embedded-tokens")]
 #[derive(RustEmbed)]
 #[folder = "./tokens/"]
 struct Tokens;
+
+/// Without the default `embedded-tokens` feature, the token files are read from disk on every
+/// load instead of being compiled in: from `GEOCODER_ABBR_DIR` if set, otherwise from this
+/// crate's `tokens` directory at its build-time location. That keeps the binary small, at the
+/// cost of shipping the files alongside it and of file I/O whenever a language is loaded.
+#[cfg(not(feature = "embedded-tokens"))]
+struct Tokens;
+
+#[cfg(not(feature = "embedded-tokens"))]
+impl Tokens {
+    fn dir() -> std::path::PathBuf {
+        match std::env::var_os(TOKENS_DIR_VAR) {
+            Some(dir) => std::path::PathBuf::from(dir),
+            None => Path::new(env!("CARGO_MANIFEST_DIR")).join("tokens")
+        }
+    }
+
+    fn iter() -> impl Iterator<Item = std::borrow::Cow<'static, str>> {
+        Tokens::files_in(&Tokens::dir()).into_iter().map(std::borrow::Cow::Owned)
+    }
+
+    fn get(file: &str) -> Option<std::borrow::Cow<'static, [u8]>> {
+        Tokens::read_in(&Tokens::dir(), file).map(std::borrow::Cow::Owned)
+    }
+
+    fn files_in(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir).into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect()
+    }
+
+    fn read_in(dir: &Path, file: &str) -> Option<Vec<u8>> {
+        fs::read(dir.join(file)).ok()
+    }
+}
+
 impl Tokens {
     pub fn codes() -> Vec<String> {
         let mut codes: Vec<String> = Tokens::iter().filter(|lang| {
             lang.contains(".json")
         }).map(|lang| {
             String::from(lang).replace(".json", "")
+        }).filter(|lang| {
+            // `<lc>.<CC>.json` files are country overlays, not languages of their own, and
+            // `global.json` is merged into languages on request
+            !lang.contains('.') && lang != GLOBAL
         }).collect();
 
         codes.sort();
@@ -25,9 +112,49 @@ impl Tokens {
                 Ok(tokens) => Ok(String::from(tokens)),
                 _ => Err(Error::TokenFileImportNotSupported(lc.to_string()))
             },
-            None => Err(Error::TokenFileImportNotSupported(lc.to_string()))
+            None => match registered().read().unwrap_or_else(|e| e.into_inner()).get(lc) {
+                Some(tokens) => Ok(String::from(*tokens)),
+                None => Err(Error::TokenFileImportNotSupported(lc.to_string()))
+            }
         }
     }
+
+    fn supports(lc: &str) -> bool {
+        Tokens::codes().iter().any(|code| code == lc)
+            || registered().read().unwrap_or_else(|e| e.into_inner()).contains_key(lc)
+    }
+}
+
+// The token file holding language-independent groups, see `ConfigOptions::include_global`.
+const GLOBAL: &str = "global";
+
+// The directory of token files: the runtime source of them without `embedded-tokens`, and
+// an overlay on the built-in ones for `config_with_env` either way.
+const TOKENS_DIR_VAR: &str = "GEOCODER_ABBR_DIR";
+
+fn registered() -> &'static RwLock<HashMap<String, &'static str>> {
+    static REGISTERED: OnceLock<RwLock<HashMap<String, &'static str>>> = OnceLock::new();
+    REGISTERED.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Makes `code` available to `config` and friends, backed by the given token JSON. Built-in
+/// languages always take precedence over registered ones, so registering a built-in code does
+/// nothing, and `config(Vec::new())` still returns only the built-in languages.
+pub fn register_language(code: &str, json: &'static str) {
+    // Built-in codes are never read from the registry, and evicting them from the shared
+    // cache would only cost other callers their cached tokens.
+    if Tokens::codes().iter().any(|lc| lc == code) {
+        return;
+    }
+    registered().write().unwrap_or_else(|e| e.into_inner()).insert(code.to_string(), json);
+    // Region tags such as "zz-US" were loaded from `code` too.
+    shared().write().unwrap_or_else(|e| e.into_inner())
+        .retain(|lc, _| lc != code && lc.split(['-', '_']).next() != Some(code));
+}
+
+fn shared() -> &'static RwLock<HashMap<String, Arc<Vec<Token>>>> {
+    static SHARED: OnceLock<RwLock<HashMap<String, Arc<Vec<Token>>>>> = OnceLock::new();
+    SHARED.get_or_init(|| RwLock::new(HashMap::new()))
 }
 
 #[derive(Debug, PartialEq)]
@@ -35,69 +162,146 @@ pub enum Error {
     LanguageCodeNotSupported(String),
     TokenFileImportNotSupported(String),
     TokenTypeNotSupported(String),
-    FancyRegexError
+    LayerNotSupported(String),
+    /// A regex token's pattern doesn't compile. Holds fancy-regex's description of the
+    /// problem, e.g. "LookBehindNotConst".
+    FancyRegexError(String),
+    /// A token file isn't valid token JSON. Holds serde_json's description of the problem.
+    JsonParse(String),
+    /// A token file imports itself, directly or through other files. Holds the import chain,
+    /// e.g. "a -> b -> a".
+    ImportCycle(String),
+    /// A language tag's region subtag names another country than the one the tokens are
+    /// scoped to, e.g. "en-GB" with `ConfigOptions::country` "US". Holds the tag.
+    RegionConflict(String),
+    /// Bytes passed to `from_binary` aren't a binary config this version can read. Only
+    /// returned with the `binary` feature, but always declared so matches on `Error` don't
+    /// depend on features.
+    BinaryDecodeError(String)
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::LanguageCodeNotSupported(lc) => write!(f, "language code not supported: {}", lc),
+            Error::TokenFileImportNotSupported(lc) => write!(f, "unable to import token file: {}", lc),
+            Error::TokenTypeNotSupported(t) => write!(f, "token type not supported: {}", t),
+            Error::LayerNotSupported(l) => write!(f, "layer not supported: {}", l),
+            Error::FancyRegexError(e) => write!(f, "invalid regex in token file: {}", e),
+            Error::JsonParse(e) => write!(f, "unable to parse token JSON: {}", e),
+            Error::ImportCycle(chain) => write!(f, "token file imports itself: {}", chain),
+            Error::RegionConflict(tag) => write!(f, "language tag region conflicts with the country: {}", tag),
+            Error::BinaryDecodeError(e) => write!(f, "unable to decode binary config: {}", e)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::JsonParse(error.to_string())
+    }
 }
 
 impl From<fancy_regex::Error> for Error {
-    fn from(_error: fancy_regex::Error) -> Self {
-        Error::FancyRegexError
+    fn from(error: fancy_regex::Error) -> Self {
+        Error::FancyRegexError(format!("{:?}", error))
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct InToken {
     tokens: Vec<String>,
     full: String,
     canonical: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     note: Option<String>,
-    #[serde(rename = "onlyCountries")]
+    #[serde(rename = "onlyCountries", skip_serializing_if = "Option::is_none")]
     only_countries: Option<Vec<String>>,
-    #[serde(rename = "onlyLayers")]
+    #[serde(rename = "onlyLayers", skip_serializing_if = "Option::is_none")]
     only_layers: Option<Vec<String>>,
-    #[serde(rename = "preferFull")]
+    #[serde(rename = "preferFull", skip_serializing_if = "Option::is_none")]
     prefer_full: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     regex: Option<bool>,
-    #[serde(rename = "skipBoundaries")]
+    #[serde(rename = "skipBoundaries", skip_serializing_if = "Option::is_none")]
     skip_boundaries: Option<bool>,
-    #[serde(rename = "skipDiacriticStripping")]
+    #[serde(rename = "skipDiacriticStripping", skip_serializing_if = "Option::is_none")]
     skip_diacritic_stripping: Option<bool>,
-    #[serde(rename = "spanBoundaries")]
+    #[serde(rename = "requiresNumber", skip_serializing_if = "Option::is_none")]
+    requires_number: Option<bool>,
+    #[serde(rename = "spanBoundaries", skip_serializing_if = "Option::is_none")]
     span_boundaries: Option<u8>,
-    #[serde(rename = "type")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     token_type: Option<String>,
+    #[serde(rename = "minLength", skip_serializing_if = "Option::is_none")]
+    min_length: Option<usize>,
+    #[serde(rename = "maxLength", skip_serializing_if = "Option::is_none")]
+    max_length: Option<usize>,
+    #[serde(rename = "asciiCanonical", skip_serializing_if = "Option::is_none")]
+    ascii_canonical: Option<String>,
+    #[serde(rename = "caseInsensitive", skip_serializing_if = "Option::is_none")]
+    case_insensitive: Option<bool>,
 }
 
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Token {
     pub tokens: Vec<String>,
     pub full: Replacer,
     pub canonical: String,
     pub note: Option<String>,
     pub only_countries: Option<Vec<String>>,
-    pub only_layers: Option<Vec<String>>,
+    pub only_layers: Option<Vec<Layer>>,
     pub prefer_full: bool,
     pub regex: bool,
     pub skip_boundaries: bool,
     pub skip_diacritic_stripping: bool,
+    /// Only replace matches with a number right before or after them, as in "Apt 4".
+    pub requires_number: bool,
     pub span_boundaries: Option<u8>,
     pub token_type: Option<TokenType>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// An unaccented spelling of `canonical`, emitted instead of it when the matched text
+    /// was plain ASCII.
+    pub ascii_canonical: Option<String>,
+    /// The code of the token file the token was loaded for, e.g. "en" or "en.US". Tokens a
+    /// file imports are tagged with the importing file's code.
+    pub language: Option<String>,
+    /// Whether the token matches regardless of case, see `ConfigOptions::case_insensitive`.
+    /// Serialized as `caseInsensitive`, so it survives a round trip through JSON.
+    pub case_insensitive: bool,
 }
 
 impl Token {
     fn new(input: InToken) -> Result<Self, Error> {
-        Ok(Token {
+        let case_insensitive = input.case_insensitive.unwrap_or(false);
+        let mut tk = Token {
             tokens: input.tokens,
             full: match input.regex {
-                Some(true) => Replacer::Regex(Regex::new(&input.full)?),
+                Some(true) => {
+                    let (unnamed, names) = strip_group_names(&input.full);
+                    Replacer::Regex {
+                        regex: Arc::new(Regex::new(&unnamed)?),
+                        pattern: input.full,
+                        names,
+                    }
+                },
                 Some(false) | None => Replacer::String(input.full),
             },
             canonical: input.canonical,
             note: input.note,
             only_countries: input.only_countries,
-            only_layers: input.only_layers,
+            only_layers: match input.only_layers {
+                None => None,
+                Some(layers) => Some(layers.iter().map(|l| Layer::from_str(l)).collect::<Result<Vec<Layer>, Error>>()?)
+            },
             prefer_full: input.prefer_full.unwrap_or(false),
             regex: input.regex.unwrap_or(false),
             skip_boundaries: input.skip_boundaries.unwrap_or(false),
             skip_diacritic_stripping: input.skip_diacritic_stripping.unwrap_or(false),
+            requires_number: input.requires_number.unwrap_or(false),
             span_boundaries: input.span_boundaries,
             token_type: match input.token_type {
                 None => None,
@@ -105,17 +309,214 @@ impl Token {
                     Ok(t) => Some(t),
                     Err(e) => return Err(e)
                 }
-            }
+            },
+            min_length: input.min_length,
+            max_length: input.max_length,
+            ascii_canonical: input.ascii_canonical,
+            language: None,
+            case_insensitive: false,
+        };
+        if case_insensitive {
+            tk.make_case_insensitive()?;
+        }
+        Ok(tk)
+    }
+
+    fn make_case_insensitive(&mut self) -> Result<(), Error> {
+        if let Replacer::Regex { regex, pattern, .. } = &mut self.full {
+            *regex = Arc::new(Regex::new(&format!("(?i){}", strip_group_names(pattern).0))?);
+        }
+        self.case_insensitive = true;
+        Ok(())
+    }
+
+    fn load(input: InToken, lc: &str) -> Result<Self, Error> {
+        Token::new(input).map(|tk| Token {
+            language: Some(lc.to_string()),
+            ..tk
         })
     }
+
+    fn to_input(&self) -> InToken {
+        InToken {
+            tokens: self.tokens.clone(),
+            full: self.full.as_str().to_string(),
+            canonical: self.canonical.clone(),
+            note: self.note.clone(),
+            only_countries: self.only_countries.clone(),
+            only_layers: self.only_layers.as_ref().map(|layers| layers.iter().map(|l| l.as_str().to_string()).collect()),
+            prefer_full: Some(self.prefer_full).filter(|b| *b),
+            regex: Some(self.regex).filter(|b| *b),
+            skip_boundaries: Some(self.skip_boundaries).filter(|b| *b),
+            skip_diacritic_stripping: Some(self.skip_diacritic_stripping).filter(|b| *b),
+            requires_number: Some(self.requires_number).filter(|b| *b),
+            span_boundaries: self.span_boundaries,
+            token_type: self.token_type.as_ref().map(|t| t.as_str().to_string()),
+            min_length: self.min_length,
+            max_length: self.max_length,
+            ascii_canonical: self.ascii_canonical.clone(),
+            case_insensitive: Some(self.case_insensitive).filter(|b| *b),
+        }
+    }
+
+    /// An identifier for the token that stays the same across releases as long as its
+    /// `canonical` and `full` forms and its `language` do: 16 hex digits of their FNV-1a hash.
+    ///
+    /// It is not unique within a language: a file may have several groups with the same
+    /// `canonical` and `full` that differ in their other fields, e.g. their forms or
+    /// `onlyCountries`, and those share an id. Tokens that differ in any of the three hashed
+    /// fields get different ids, barring a 64-bit hash collision.
+    pub fn id(&self) -> String {
+        let language = self.language.as_deref().unwrap_or("");
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for part in [self.canonical.as_str(), self.full.as_str(), language] {
+            for byte in part.bytes().chain(std::iter::once(0)) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        format!("{:016x}", hash)
+    }
+
+    /// The whitespace-separated words of `full`, e.g. `["Post", "Office", "Box"]`. For regex
+    /// tokens these are the words of the pattern.
+    pub fn words(&self) -> Vec<&str> {
+        self.full.as_str().split_whitespace().collect()
+    }
+
+    pub fn word_count(&self) -> usize {
+        self.full.as_str().split_whitespace().count()
+    }
+
+    /// The form to emit when rewriting text in the given direction: `full` when expanding,
+    /// `canonical` when abbreviating unless the token sets `prefer_full`. Regex tokens always
+    /// emit their `canonical` replacement.
+    pub fn preferred_form(&self, direction: Direction) -> &str {
+        match (&self.full, direction) {
+            (Replacer::Regex { .. }, _) => &self.canonical,
+            (Replacer::String(full), Direction::Expand) => full,
+            (Replacer::String(full), Direction::Abbreviate) if self.prefer_full => full,
+            (Replacer::String(_), Direction::Abbreviate) => &self.canonical
+        }
+    }
+}
+
+/// Serializes a token in the token file format: regex tokens as their pattern, and only the
+/// optional properties that are set.
+impl Serialize for Token {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_input().serialize(serializer)
+    }
+}
+
+/// Reads a token group in the shape of the token files. The token has no `language`, as it
+/// wasn't loaded for one, and matches case-sensitively unless the group sets
+/// `caseInsensitive`.
+impl<'de> Deserialize<'de> for Token {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Token::new(InToken::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
 }
 
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Direction {
+    #[default]
+    Abbreviate,
+    Expand
+}
+
+/// A token's `full` form. Compiled regexes are immutable, so clones of a token share them
+/// rather than copying the automaton.
+#[derive(Clone)]
 pub enum Replacer {
    String(String),
-   Regex(Regex)
+   Regex {
+       regex: Arc<Regex>,
+       pattern: String,
+       /// The index of each named group in `pattern`, for `${name}` references in the
+       /// canonical template. `regex` itself is compiled with the names stripped.
+       names: HashMap<String, usize>
+   }
+}
+
+// fancy-regex has no named groups, so `(?P<name>...)` and `(?<name>...)` are compiled as plain
+// groups and the names are resolved to group indices here.
+fn strip_group_names(pattern: &str) -> (String, HashMap<String, usize>) {
+    let mut out = String::with_capacity(pattern.len());
+    let mut names = HashMap::new();
+    let mut groups = 0;
+    let mut in_class = false;
+    let mut chars = pattern.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        out.push(c);
+        match c {
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    out.push(escaped);
+                }
+            },
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '(' if !in_class => {
+                let rest = &pattern[i + 1..];
+                let name = rest.strip_prefix("?P<")
+                    .or_else(|| rest.strip_prefix("?<").filter(|r| !r.starts_with('=') && !r.starts_with('!')));
+                match name.and_then(|name| name.find('>').map(|close| &name[..close])) {
+                    Some(name) => {
+                        groups += 1;
+                        names.insert(name.to_string(), groups);
+                        let close = i + 1 + rest.find('>').unwrap_or(0);
+                        while chars.next_if(|&(j, _)| j <= close).is_some() {}
+                    },
+                    None if rest.starts_with('?') => (),
+                    None => groups += 1
+                }
+            },
+            _ => ()
+        }
+    }
+    (out, names)
+}
+
+impl Replacer {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Replacer::String(full) => full,
+            Replacer::Regex { pattern, .. } => pattern
+        }
+    }
+}
+
+impl PartialEq for Replacer {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Replacer::String(a), Replacer::String(b)) => a == b,
+            (Replacer::Regex { pattern: a, .. }, Replacer::Regex { pattern: b, .. }) => a == b,
+            _ => false
+        }
+    }
+}
+
+impl Eq for Replacer {}
+
+// Consistent with `PartialEq`: a regex replacer hashes as its pattern.
+impl std::hash::Hash for Replacer {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        self.as_str().hash(state);
+    }
+}
+
+impl std::fmt::Debug for Replacer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Replacer::String(full) => f.debug_tuple("String").field(full).finish(),
+            Replacer::Regex { pattern, .. } => f.debug_tuple("Regex").field(pattern).finish()
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum TokenType {
     PostalBox,
     Cardinal,
@@ -126,7 +527,10 @@ pub enum TokenType {
     Determiner
 }
 
-impl TokenType {
+impl FromStr for TokenType {
+    type Err = Error;
+
+    /// Parses the `type` string used in the token files, e.g. "box".
     fn from_str(s: &str) -> Result<TokenType, Error> {
         match s {
             "box" => Ok(TokenType::PostalBox),
@@ -141,100 +545,1795 @@ impl TokenType {
     }
 }
 
-pub fn config(v: Vec<String>) -> Result<HashMap<String, Vec<Token>>, Error> {
-    if v.is_empty() {
-        return Ok(prepare(Tokens::codes())?)
+impl TokenType {
+    /// The `type` string used for this type in the token files, e.g. "box".
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenType::PostalBox => "box",
+            TokenType::Cardinal => "cardinal",
+            TokenType::Number => "number",
+            TokenType::Ordinal => "ordinal",
+            TokenType::Unit => "unit",
+            TokenType::Way => "way",
+            TokenType::Determiner => "determiner"
+        }
     }
-    for lc in &v {
-        if !Tokens::codes().contains(lc) {
-            return Err(Error::LanguageCodeNotSupported(lc.to_string()))
+}
+
+impl std::fmt::Display for TokenType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A geocoding layer a token can be restricted to with `onlyLayers`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Layer {
+    Country,
+    Region,
+    Postcode,
+    District,
+    Place,
+    Locality,
+    Neighborhood,
+    Address,
+    Poi
+}
+
+impl FromStr for Layer {
+    type Err = Error;
+
+    /// Parses the `onlyLayers` string used in the token files, e.g. "address".
+    fn from_str(s: &str) -> Result<Layer, Error> {
+        match s {
+            "country" => Ok(Layer::Country),
+            "region" => Ok(Layer::Region),
+            "postcode" => Ok(Layer::Postcode),
+            "district" => Ok(Layer::District),
+            "place" => Ok(Layer::Place),
+            "locality" => Ok(Layer::Locality),
+            "neighborhood" => Ok(Layer::Neighborhood),
+            "address" => Ok(Layer::Address),
+            "poi" => Ok(Layer::Poi),
+            _ => Err(Error::LayerNotSupported(s.to_string()))
         }
     }
-    Ok(prepare(v)?)
 }
 
-fn prepare(v: Vec<String>) -> Result<HashMap<String, Vec<Token>>, Error> {
-    let mut map = HashMap::new();
-    for lc in &v {
-        let parsed : Vec<InToken> = serde_json::from_str(Tokens::import(lc)?.as_str())
-            .expect("unable to parse token JSON");
-        let mut tokens = Vec::new();
-        for tk in &parsed {
-            tokens.push(Token::new(tk.clone())?);
+impl Layer {
+    /// The `onlyLayers` string used for this layer in the token files, e.g. "address".
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Layer::Country => "country",
+            Layer::Region => "region",
+            Layer::Postcode => "postcode",
+            Layer::District => "district",
+            Layer::Place => "place",
+            Layer::Locality => "locality",
+            Layer::Neighborhood => "neighborhood",
+            Layer::Address => "address",
+            Layer::Poi => "poi"
         }
-        map.insert(lc.clone(), tokens);
     }
-    Ok(map)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
+impl std::fmt::Display for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
-    #[test]
-    fn test_config() {
-        let lcs = config(vec![String::from("de"), String::from("en")]).unwrap();
-        assert_eq!(lcs.len(), 2);
-        assert!(lcs.contains_key("de"));
-        assert!(lcs.contains_key("en"));
+/// The codes of the built-in languages, sorted; these are what `config(Vec::new())` loads.
+/// Languages added with `register_language` aren't included.
+pub fn supported_languages() -> Vec<String> {
+    Tokens::codes()
+}
 
-        let empty_lc = config(Vec::new()).unwrap();
-        let every_lc = prepare(Tokens::codes()).unwrap();
-        assert_eq!(empty_lc.len(), every_lc.len());
-        for lc in Tokens::codes() {
-            assert!(empty_lc.contains_key(&lc));
+/// Loads the languages in `v`, or every built-in language when it's empty. Each language is
+/// parsed once and cached, see `config_shared`; later calls clone the cached tokens, whose
+/// compiled regexes are shared rather than recompiled.
+pub fn config(v: Vec<String>) -> Result<HashMap<String, Vec<Token>>, Error> {
+    config_with(ConfigOptions::new().languages(v).build())
+}
+
+/// What `config_with` loads. The defaults load every built-in language as `config` does.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConfigOptions {
+    /// The languages to load; empty means every built-in language.
+    pub languages: Vec<String>,
+    /// Scopes every language to an ISO 3166-1 alpha-2 country, as `config_for_country` does.
+    /// Region tags in `languages` must name the same country, or loading fails with
+    /// `Error::RegionConflict`.
+    pub country: Option<String>,
+    /// Appends the language-independent groups from `global.json` to every language. Since
+    /// they come after the language's own groups, the language's groups win wherever both
+    /// match the same text.
+    pub include_global: bool,
+    /// Makes every token match regardless of case, so "AVE", "ave" and "Ave" all match
+    /// "Ave". Plain-string forms compare characters by their lowercase form where that is a
+    /// single character, so e.g. Turkish "İ" only matches itself; regex tokens are compiled
+    /// with the `i` flag.
+    pub case_insensitive: bool,
+    /// Keeps only the tokens of these types, dropping untyped tokens too.
+    pub token_types: Option<Vec<TokenType>>,
+    /// Keeps only the tokens that apply to this layer: those without `onlyLayers` and those
+    /// listing it.
+    pub layer: Option<Layer>,
+    /// Keeps regex tokens whose pattern fails to compile because of a lookbehind fancy-regex
+    /// can't run, such as one of variable length, as plain-string tokens: `regex` is cleared
+    /// and the token matches the surface forms of its `tokens` verbatim. Without it such a
+    /// token fails the whole load, as any other invalid pattern still does.
+    pub lookaround_fallback: bool,
+}
+
+impl ConfigOptions {
+    pub fn new() -> Self {
+        ConfigOptions::default()
+    }
+
+    pub fn languages(mut self, languages: Vec<String>) -> Self {
+        self.languages = languages;
+        self
+    }
+
+    pub fn country(mut self, country: &str) -> Self {
+        self.country = Some(country.to_string());
+        self
+    }
+
+    pub fn include_global(mut self, include_global: bool) -> Self {
+        self.include_global = include_global;
+        self
+    }
+
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    pub fn token_types(mut self, token_types: Vec<TokenType>) -> Self {
+        self.token_types = Some(token_types);
+        self
+    }
+
+    pub fn layer(mut self, layer: Layer) -> Self {
+        self.layer = Some(layer);
+        self
+    }
+
+    pub fn lookaround_fallback(mut self, lookaround_fallback: bool) -> Self {
+        self.lookaround_fallback = lookaround_fallback;
+        self
+    }
+
+    /// Ends a chain of setters; the options are ready to use as they are.
+    pub fn build(self) -> Self {
+        self
+    }
+}
+
+pub fn config_with(options: ConfigOptions) -> Result<HashMap<String, Vec<Token>>, Error> {
+    let languages = if options.languages.is_empty() {
+        Tokens::codes()
+    } else {
+        for lc in &options.languages {
+            if !Tokens::supports(split_region(lc).0) {
+                return Err(Error::LanguageCodeNotSupported(lc.to_string()))
+            }
+        }
+        options.languages
+    };
+    let mut map = prepare(languages, options.lookaround_fallback)?;
+    if let Some(country) = &options.country {
+        for (lc, tokens) in map.iter_mut() {
+            scope_to_country(lc, tokens, country)?;
+        }
+    }
+    if options.include_global {
+        let global = parse(GLOBAL, &Tokens::import(GLOBAL)?)?;
+        for tokens in map.values_mut() {
+            tokens.extend(global.iter().cloned());
+        }
+    }
+    if let Some(types) = &options.token_types {
+        for tokens in map.values_mut() {
+            tokens.retain(|tk| tk.token_type.is_some_and(|t| types.contains(&t)));
+        }
+    }
+    if let Some(layer) = options.layer {
+        for tokens in map.values_mut() {
+            tokens.retain(|tk| tk.only_layers.as_ref().is_none_or(|layers| layers.contains(&layer)));
+        }
+    }
+    if options.case_insensitive {
+        for tk in map.values_mut().flatten() {
+            tk.make_case_insensitive()?;
         }
     }
+    Ok(map)
+}
 
-    #[test]
-    #[should_panic(expected = "LanguageCodeNotSupported(\"zz\")")]
-    fn fail_config() {
-        config(vec![String::from("zz")]).unwrap();
+#[derive(Debug)]
+pub struct PrepareReport {
+    pub tokens: HashMap<String, Vec<Token>>,
+    /// Every regex token left out of `tokens` because its pattern doesn't compile (e.g. a
+    /// lookbehind of variable length), as its language, its `full` pattern and the compile
+    /// error, in load order.
+    pub errors: Vec<(String, String, String)>,
+    /// The `full` patterns of the tokens in `errors` that fail over a lookaround fancy-regex
+    /// doesn't support, by language. Every loaded language has an entry, if only an empty one.
+    pub filtered: HashMap<String, Vec<String>>,
+}
+
+/// Like `config`, but instead of failing on a regex token that doesn't compile, leaves it out
+/// and reports it in `PrepareReport::errors`, and in `PrepareReport::filtered` as well if its
+/// lookaround is to blame.
+pub fn prepare_report(v: Vec<String>) -> Result<PrepareReport, Error> {
+    let v = if v.is_empty() { Tokens::codes() } else { v };
+    let mut report = PrepareReport {
+        tokens: HashMap::new(),
+        errors: Vec::new(),
+        filtered: HashMap::new(),
+    };
+    for lc in v {
+        let (base, region) = split_region(&lc);
+        if !Tokens::supports(base) {
+            return Err(Error::LanguageCodeNotSupported(lc));
+        }
+        let (mut tokens, filtered) = parse_filtered(base, &Tokens::import(base)?)?;
+        if let Some(region) = region {
+            scope_to_country(base, &mut tokens, region)?;
+        }
+        let lookaround = filtered.iter().filter(|(_, _, lookaround)| *lookaround).map(|(full, _, _)| full.clone());
+        report.filtered.insert(lc.clone(), lookaround.collect());
+        report.errors.extend(filtered.into_iter().map(|(full, message, _)| (lc.clone(), full, message)));
+        report.tokens.insert(lc, tokens);
     }
+    Ok(report)
+}
 
-    #[test]
-    fn test_all_lcs() {
-        let mut fs_lcs = read_files();
-        alphanumeric_sort::sort_str_slice(&mut fs_lcs);
-        assert_eq!(Tokens::codes(), fs_lcs);
+// The number of regex tokens in `lc`'s token file that don't compile, see `prepare_report`.
+// Languages that can't be loaded have none.
+fn filtered_regex_count(lc: &str) -> usize {
+    let base = split_region(lc).0;
+    Tokens::import(base).ok()
+        .and_then(|json| parse_filtered(base, &json).ok())
+        .map_or(0, |(_, filtered)| filtered.len())
+}
+
+/// Like `config`, but with `include_global` set, the language-independent groups from
+/// `global.json` are appended to every language, see `ConfigOptions::include_global`.
+#[deprecated(note = "use `config_with` with `ConfigOptions::include_global`")]
+pub fn config_with_options(v: Vec<String>, include_global: bool) -> Result<HashMap<String, Vec<Token>>, Error> {
+    config_with(ConfigOptions::new().languages(v).include_global(include_global).build())
+}
+
+/// Loads a single language, without the map `config` builds around it.
+///
+/// `lc` may also be a BCP 47 style tag with a region subtag, such as "en-US" or "pt_BR":
+/// the base language is loaded and scoped to the region's country as `config_for_country`
+/// does. Script and other subtags are ignored, so "sr-Latn-RS" loads "sr" for "RS".
+pub fn config_one(lc: &str) -> Result<Vec<Token>, Error> {
+    load_language(lc, false)
+}
+
+fn load_language(lc: &str, lookaround_fallback: bool) -> Result<Vec<Token>, Error> {
+    let (base, region) = split_region(lc);
+    if !Tokens::supports(base) {
+        return Err(Error::LanguageCodeNotSupported(lc.to_string()));
+    }
+    let mut tokens = parse_with(base, &Tokens::import(base)?, lookaround_fallback)?;
+    if let Some(region) = region {
+        scope_to_country(base, &mut tokens, region)?;
     }
+    Ok(tokens)
+}
 
-    #[test]
-    fn test_prepare() {
-        let lcs = prepare(vec![String::from("de"), String::from("en")]).unwrap();
-        assert_eq!(lcs.len(), 2);
-        assert!(lcs.contains_key("de"));
-        assert!(lcs.contains_key("en"));
+// Splits a language tag into its base language and region subtag, if it has one. Codes the
+// crate knows as they are, registered ones included, are never split.
+fn split_region(tag: &str) -> (&str, Option<&str>) {
+    if Tokens::supports(tag) {
+        return (tag, None);
     }
+    let mut subtags = tag.split(['-', '_']);
+    let base = subtags.next().unwrap_or(tag);
+    let region = subtags.find(|s| s.len() == 2 && s.chars().all(|c| c.is_ascii_alphabetic()));
+    (base, region)
+}
 
-    #[test]
-    #[should_panic(expected = "TokenFileImportNotSupported(\"zz\")")]
-    fn fail_import() {
-        Tokens::import("zz").unwrap();
+/// Like `config`, but each language is only parsed the first time it is asked for; later
+/// calls, from any thread, hand back the same shared token list.
+pub fn config_shared(v: Vec<String>) -> Result<HashMap<String, Arc<Vec<Token>>>, Error> {
+    let v = if v.is_empty() { Tokens::codes() } else { v };
+    let mut map = HashMap::new();
+    for lc in v {
+        let cached = shared().read().unwrap_or_else(|e| e.into_inner()).get(&lc).cloned();
+        let tokens = match cached {
+            Some(tokens) => tokens,
+            None => {
+                let tokens = Arc::new(config_one(&lc)?);
+                shared().write().unwrap_or_else(|e| e.into_inner())
+                    .entry(lc.clone())
+                    .or_insert(tokens)
+                    .clone()
+            }
+        };
+        map.insert(lc, tokens);
     }
+    Ok(map)
+}
 
-    #[test]
-    fn test_token_values() {
-        let map = config(Vec::new()).unwrap();
+/// Loads `lc` and appends `extra` after its tokens, so built-in tokens take precedence where
+/// both match the same text. `extra` is appended as is; for a language made up of custom
+/// tokens only, see `register_language`.
+pub fn config_with_custom(lc: &str, extra: Vec<Token>) -> Result<Vec<Token>, Error> {
+    let mut tokens = config_one(lc)?;
+    tokens.extend(extra);
+    Ok(tokens)
+}
 
-        for lc in map.values() {
-            for tk in lc {
-                assert!(!tk.tokens.is_empty());
-                if let Some(l) = &tk.only_layers {
-                    assert_eq!(l[0], "address");
-                    assert_eq!(l.len(), 1);
-                }
+/// Like `config`, but reads `<dir>/<lc>.json` at runtime instead of the built-in files. An
+/// empty `v` loads every `.json` file in `dir`.
+pub fn config_from_dir(dir: &Path, v: Vec<String>) -> Result<HashMap<String, Vec<Token>>, Error> {
+    if v.is_empty() {
+        return prepare_dir(dir);
+    }
+    let mut map = HashMap::new();
+    for lc in v {
+        let tokens = prepare_dir_file(dir, &lc)?;
+        map.insert(lc, tokens);
+    }
+    Ok(map)
+}
+
+/// Like `config`, but any `<lc>.json` in `dir` replaces the built-in file for that language,
+/// and languages that only exist in `dir` can be requested too.
+pub fn config_with_overrides(dir: &Path, v: Vec<String>) -> Result<HashMap<String, Vec<Token>>, Error> {
+    let mut overrides = prepare_dir(dir)?;
+    let v = if v.is_empty() {
+        let mut v = Tokens::codes();
+        v.extend(overrides.keys().filter(|lc| !Tokens::codes().contains(lc)).cloned());
+        v
+    } else {
+        v
+    };
+    let mut map = HashMap::new();
+    for lc in v {
+        let tokens = match overrides.remove(&lc) {
+            Some(tokens) => tokens,
+            None => config(vec![lc.clone()])?.remove(&lc).unwrap_or_default()
+        };
+        map.insert(lc, tokens);
+    }
+    Ok(map)
+}
+
+/// Behaves like `config_with_overrides` when `GEOCODER_ABBR_DIR` is set, and like `config`
+/// otherwise.
+pub fn config_with_env(v: Vec<String>) -> Result<HashMap<String, Vec<Token>>, Error> {
+    config_with_dir(std::env::var_os(TOKENS_DIR_VAR).as_deref(), v)
+}
+
+// `config_with_env` given the variable's value, so tests needn't touch the environment.
+fn config_with_dir(dir: Option<&std::ffi::OsStr>, v: Vec<String>) -> Result<HashMap<String, Vec<Token>>, Error> {
+    match dir {
+        Some(dir) => config_with_overrides(Path::new(dir), v),
+        None => config(v)
+    }
+}
+
+/// Loads the tokens for `lc` as used in `country`, an ISO 3166-1 alpha-2 code.
+///
+/// On top of the base `<lc>.json` file, a `<lc>.<CC>.json` overlay (e.g. `en.US.json`) is
+/// merged in when one exists: an overlay group replaces the base group with the same `full`
+/// form, and the remaining overlay groups are appended after the base tokens.
+///
+/// Groups whose `onlyCountries` doesn't list `country` (compared case-insensitively) are
+/// left out. A region tag such as "en-GB" for `lc` must name `country` itself.
+pub fn config_for_country(lc: &str, country: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = config_one(lc)?;
+    scope_to_country(lc, &mut tokens, country)?;
+    Ok(tokens)
+}
+
+// `lc` may be a region tag, whose tokens were already scoped to its region when loaded; scoping
+// them again to the same country changes nothing, and to another one is refused.
+fn scope_to_country(lc: &str, tokens: &mut Vec<Token>, country: &str) -> Result<(), Error> {
+    let (base, region) = split_region(lc);
+    if region.is_some_and(|region| !region.eq_ignore_ascii_case(country)) {
+        return Err(Error::RegionConflict(lc.to_string()));
+    }
+    let overlay = format!("{}.{}", base, country.to_uppercase());
+    if let Ok(json) = Tokens::import(&overlay) {
+        for tk in parse(&overlay, &json)? {
+            match tokens.iter().position(|base| base.full == tk.full) {
+                Some(i) => tokens[i] = tk,
+                None => tokens.push(tk)
+            }
+        }
+    }
+    tokens.retain(|tk| match &tk.only_countries {
+        Some(countries) => countries.iter().any(|cc| cc.eq_ignore_ascii_case(country)),
+        None => true
+    });
+    Ok(())
+}
+
+pub fn config_typed_only(v: Vec<String>) -> Result<HashMap<String, Vec<Token>>, Error> {
+    let mut map = config(v)?;
+    for tokens in map.values_mut() {
+        tokens.retain(|tk| tk.token_type.is_some());
+    }
+    Ok(map)
+}
+
+/// Yields every token in `map` with the code of the language it's listed under, languages
+/// in code order and tokens in their file order.
+pub fn all_tokens(map: &HashMap<String, Vec<Token>>) -> impl Iterator<Item = (&str, &Token)> {
+    let mut lcs: Vec<&String> = map.keys().collect();
+    lcs.sort();
+    lcs.into_iter().flat_map(move |lc| map[lc].iter().map(move |tk| (lc.as_str(), tk)))
+}
+
+/// Flattens `map` into one token list, languages in `order` first and any others after them
+/// in code order. Where several languages list the same token, only its first occurrence is
+/// kept; tokens are compared as `PartialEq` does except for `language`, which tells them
+/// apart by definition.
+pub fn merge(map: HashMap<String, Vec<Token>>, order: &[String]) -> Vec<Token> {
+    let mut map = map;
+    let mut rest: Vec<String> = map.keys().filter(|lc| !order.contains(lc)).cloned().collect();
+    rest.sort();
+    let mut merged: Vec<Token> = Vec::new();
+    let mut languages = Vec::new();
+    for lc in order.iter().chain(&rest) {
+        for mut tk in map.remove(lc).unwrap_or_default() {
+            let language = tk.language.take();
+            if !merged.contains(&tk) {
+                merged.push(tk);
+                languages.push(language);
             }
         }
     }
+    for (tk, language) in merged.iter_mut().zip(languages) {
+        tk.language = language;
+    }
+    merged
+}
+
+pub fn configs_equal_unordered(a: &HashMap<String, Vec<Token>>, b: &HashMap<String, Vec<Token>>) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().all(|(lc, a_tokens)| match b.get(lc) {
+        Some(b_tokens) => tokens_equal_unordered(a_tokens, b_tokens),
+        None => false
+    })
+}
+
+fn tokens_equal_unordered(a: &[Token], b: &[Token]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut used = vec![false; b.len()];
+    a.iter().all(|tk| {
+        match (0..b.len()).find(|&i| !used[i] && b[i] == *tk) {
+            Some(i) => {
+                used[i] = true;
+                true
+            },
+            None => false
+        }
+    })
+}
+
+// Loads `v`, from the `config_shared` cache unless the fallback changes what is parsed.
+fn prepare(v: Vec<String>, lookaround_fallback: bool) -> Result<HashMap<String, Vec<Token>>, Error> {
+    if !lookaround_fallback {
+        return Ok(config_shared(v)?.into_iter().map(|(lc, tokens)| (lc, tokens.as_ref().clone())).collect());
+    }
+    let mut map = HashMap::new();
+    for lc in v {
+        let tokens = load_language(&lc, lookaround_fallback)?;
+        map.insert(lc, tokens);
+    }
+    Ok(map)
+}
+
+fn prepare_dir(dir: &Path) -> Result<HashMap<String, Vec<Token>>, Error> {
+    let entries = fs::read_dir(dir)
+        .map_err(|_| Error::TokenFileImportNotSupported(dir.display().to_string()))?;
+    let mut map = HashMap::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let lc = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(lc) => lc.to_string(),
+            None => continue
+        };
+        map.insert(lc.clone(), prepare_dir_file(dir, &lc)?);
+    }
+    Ok(map)
+}
+
+// Loads `<dir>/<lc>.json`. Its imports are looked up in `dir` first, then among the built-in
+// and registered languages.
+fn prepare_dir_file(dir: &Path, lc: &str) -> Result<Vec<Token>, Error> {
+    let json = read_file(&dir.join(format!("{}.json", lc)))
+        .ok_or_else(|| Error::TokenFileImportNotSupported(lc.to_string()))?;
+    let import = |name: &str| match read_file(&dir.join(format!("{}.json", name))) {
+        Some(json) => Ok(json),
+        None => Tokens::import(name)
+    };
+    read_tokens(&json, &import, &mut vec![lc.to_string()])?
+        .into_iter()
+        .map(|tk| Token::load(tk, lc))
+        .collect()
+}
+
+fn read_file(path: &Path) -> Option<String> {
+    fs::read(path).ok().and_then(decode)
+}
+
+// Token files edited with Windows tooling often arrive as UTF-16 or with a UTF-8 byte order
+// mark, so honor a BOM if there is one and fall back to plain UTF-8.
+fn decode(bytes: Vec<u8>) -> Option<String> {
+    let utf16 = |bytes: &[u8], unit: fn([u8; 2]) -> u16| {
+        if !bytes.len().is_multiple_of(2) {
+            return None;
+        }
+        let units: Vec<u16> = bytes.chunks(2).map(|pair| unit([pair[0], pair[1]])).collect();
+        String::from_utf16(&units).ok()
+    };
+    match bytes.as_slice() {
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8(rest.to_vec()).ok(),
+        _ => String::from_utf8(bytes).ok()
+    }
+}
+
+// A token file is either a plain list of tokens or an object that also names the files
+// whose tokens come before its own.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum InFile {
+    Tokens(Vec<InToken>),
+    WithImports {
+        imports: Vec<String>,
+        tokens: Vec<InToken>
+    }
+}
+
+// Reads a token file, resolving its imports (recursively) with `import`. `chain` holds the
+// names of the files currently being read, starting with this one.
+fn read_tokens(json: &str, import: &dyn Fn(&str) -> Result<String, Error>, chain: &mut Vec<String>) -> Result<Vec<InToken>, Error> {
+    let (imports, tokens) = match serde_json::from_str(json)? {
+        InFile::Tokens(tokens) => (Vec::new(), tokens),
+        InFile::WithImports { imports, tokens } => (imports, tokens)
+    };
+    let mut all = Vec::new();
+    for name in imports {
+        chain.push(name.clone());
+        if chain[..chain.len() - 1].contains(&name) {
+            return Err(Error::ImportCycle(chain.join(" -> ")));
+        }
+        all.extend(read_tokens(&import(&name)?, import, chain)?);
+        chain.pop();
+    }
+    all.extend(tokens);
+    Ok(all)
+}
+
+// A regex token's `full` pattern, why it didn't compile and whether its lookaround is why.
+type Filtered = (String, String, bool);
+
+// Like `parse`, but regex tokens whose pattern doesn't compile are set aside, along with
+// the compile error, instead of failing the whole file.
+fn parse_filtered(lc: &str, json: &str) -> Result<(Vec<Token>, Vec<Filtered>), Error> {
+    let parsed = read_tokens(json, &Tokens::import, &mut vec![lc.to_string()])?;
+    let mut tokens = Vec::new();
+    let mut filtered = Vec::new();
+    for tk in parsed {
+        let full = tk.full.clone();
+        let lookaround = unsupported_lookaround(&tk);
+        match Token::load(tk, lc) {
+            Ok(tk) => tokens.push(tk),
+            Err(Error::FancyRegexError(message)) => filtered.push((full, message, lookaround)),
+            Err(e) => return Err(e)
+        }
+    }
+    Ok((tokens, filtered))
+}
+
+// Whether `tk` is a regex token whose pattern fancy-regex refuses because of a lookaround it
+// doesn't support, a lookbehind of variable length.
+fn unsupported_lookaround(tk: &InToken) -> bool {
+    tk.regex == Some(true)
+        && matches!(Regex::new(&strip_group_names(&tk.full).0), Err(fancy_regex::Error::LookBehindNotConst))
+}
+
+fn parse(lc: &str, json: &str) -> Result<Vec<Token>, Error> {
+    parse_with(lc, json, false)
+}
+
+// See `ConfigOptions::lookaround_fallback`.
+fn parse_with(lc: &str, json: &str, lookaround_fallback: bool) -> Result<Vec<Token>, Error> {
+    let parsed = read_tokens(json, &Tokens::import, &mut vec![lc.to_string()])?;
+    let mut tokens = Vec::new();
+    for tk in parsed {
+        if lookaround_fallback && unsupported_lookaround(&tk) {
+            tokens.push(Token::load(plain_fallback(InToken { regex: None, ..tk }), lc)?);
+        } else {
+            tokens.push(Token::load(tk, lc)?);
+        }
+    }
+    Ok(tokens)
+}
+
+// Turns a regex token into the plain-string token `lookaround_fallback` keeps in its place.
+// `full` must be readable text for `expand`, so it becomes the pattern without its
+// lookarounds where that leaves a literal, and otherwise the longest surface form.
+fn plain_fallback(mut tk: InToken) -> InToken {
+    let pattern = std::mem::take(&mut tk.full);
+    tk.tokens.retain(|form| *form != pattern);
+    tk.full = match literal_pattern(&pattern) {
+        Some(literal) if !literal.is_empty() => literal,
+        _ => tk.tokens.iter().max_by_key(|form| form.chars().count()).cloned().unwrap_or_else(|| tk.canonical.clone())
+    };
+    tk
+}
+
+// The text `pattern` matches once its lookaround groups are dropped, if that is a single
+// literal string: escaped punctuation is unescaped, and anything else with a special meaning
+// gives `None`.
+fn literal_pattern(pattern: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) if !escaped.is_alphanumeric() => out.push(escaped),
+                _ => return None
+            },
+            '(' if ["?=", "?!", "?<=", "?<!"].iter().any(|open| chars.clone().collect::<String>().starts_with(open)) => {
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next()? {
+                        '\\' => { chars.next()?; },
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => ()
+                    }
+                }
+            },
+            '.' | '^' | '$' | '|' | '?' | '*' | '+' | '(' | ')' | '[' | ']' | '{' | '}' => return None,
+            c => out.push(c)
+        }
+    }
+    Some(out)
+}
+
+/// Runs every regex token in `config` once and returns how long that took. Regexes are
+/// compiled when the config is loaded, so this mostly measures first-use cost, but it gives
+/// services a single place to pay it at startup.
+pub fn warmup(config: &HashMap<String, Vec<Token>>) -> Duration {
+    let start = Instant::now();
+    for tokens in config.values() {
+        for tk in tokens {
+            if let Replacer::Regex { regex, .. } = &tk.full {
+                let _ = regex.is_match("");
+            }
+        }
+    }
+    start.elapsed()
+}
+
+/// Merges the plain-string tokens that share a canonical form into the first of them, which
+/// gains the surface forms of the others. All other metadata, `full` and `prefer_full`
+/// included, is kept from that first token, even where the merged ones differ. Regex tokens
+/// are passed through as they are.
+pub fn coalesce_by_canonical(tokens: Vec<Token>) -> Vec<Token> {
+    let mut coalesced: Vec<Token> = Vec::new();
+    let mut by_canonical: HashMap<String, usize> = HashMap::new();
+    for tk in tokens {
+        if tk.regex {
+            coalesced.push(tk);
+            continue;
+        }
+        match by_canonical.get(&tk.canonical) {
+            Some(&i) => {
+                for form in tk.tokens {
+                    if !coalesced[i].tokens.contains(&form) {
+                        coalesced[i].tokens.push(form);
+                    }
+                }
+            },
+            None => {
+                by_canonical.insert(tk.canonical.clone(), coalesced.len());
+                coalesced.push(tk);
+            }
+        }
+    }
+    coalesced
+}
+
+/// Sorts `tokens` so the most frequent ones, per `freq` keyed by canonical form, come first.
+/// Tokens missing from `freq` count as 0; equally frequent tokens keep their order.
+pub fn order_by_frequency(mut tokens: Vec<Token>, freq: &HashMap<String, u32>) -> Vec<Token> {
+    tokens.sort_by_key(|tk| std::cmp::Reverse(freq.get(&tk.canonical).copied().unwrap_or(0)));
+    tokens
+}
+
+/// Resolves a `TokenMatch::token_index` back to its token. Indices are positions in the
+/// token slice the tokenizer was built from, so they stay valid for as long as that `Vec<Token>`
+/// isn't reordered or modified.
+pub fn token_at(tokens: &[Token], index: usize) -> Option<&Token> {
+    tokens.get(index)
+}
+
+/// Maps every surface form of the plain-string tokens in `tokens` to the positions of the
+/// tokens listing it. A form several tokens share, like "St" for both "Street" and "Saint",
+/// maps to all of them in slice order, so the first index is the one a `Tokenizer` prefers.
+/// Forms are taken verbatim; regex tokens and empty forms are left out.
+pub fn index(tokens: &[Token]) -> HashMap<String, Vec<usize>> {
+    let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, tk) in tokens.iter().enumerate().filter(|(_, tk)| !tk.regex) {
+        for form in tk.tokens.iter().filter(|form| !form.is_empty()) {
+            let indices = index.entry(form.clone()).or_default();
+            if indices.last() != Some(&i) {
+                indices.push(i);
+            }
+        }
+    }
+    index
+}
+
+/// Returns every token in `tokens` whose surface forms include `surface` exactly, in slice
+/// order. For repeated lookups over the same tokens, build an `index` instead.
+pub fn find<'a>(tokens: &'a [Token], surface: &str) -> Vec<&'a Token> {
+    tokens.iter().filter(|tk| tk.tokens.iter().any(|form| form == surface)).collect()
+}
+
+/// Like `find`, but compares surface forms case-insensitively, so "blvd" finds "Blvd".
+pub fn find_ci<'a>(tokens: &'a [Token], surface: &str) -> Vec<&'a Token> {
+    let surface = surface.to_lowercase();
+    tokens.iter().filter(|tk| tk.tokens.iter().any(|form| form.to_lowercase() == surface)).collect()
+}
+
+/// The codes of the languages in `map` with a token listing `surface` exactly, sorted.
+pub fn languages_for_surface(map: &HashMap<String, Vec<Token>>, surface: &str) -> Vec<String> {
+    let mut lcs: Vec<String> = map.iter()
+        .filter(|(_, tokens)| !find(tokens, surface).is_empty())
+        .map(|(lc, _)| lc.clone())
+        .collect();
+    lcs.sort();
+    lcs
+}
+
+pub fn regex_tokens(tokens: &[Token]) -> Vec<&Token> {
+    tokens.iter().filter(|tk| tk.regex).collect()
+}
+
+pub fn literal_tokens(tokens: &[Token]) -> Vec<&Token> {
+    tokens.iter().filter(|tk| !tk.regex).collect()
+}
+
+pub fn geo_restricted_tokens(tokens: &[Token]) -> Vec<&Token> {
+    tokens.iter().filter(|tk| tk.only_countries.is_some()).collect()
+}
+
+/// Serializes every built-in language into one JSON object from language code to token
+/// array, with the languages in code order. `config_from_export` reads it back.
+pub fn export_all() -> Result<String, Error> {
+    config_json(Vec::new())
+}
+
+// TODO: a `wasm` feature with `#[wasm_bindgen]` wrappers over `config_json` and
+// `abbreviate_for_language`, an `Error` to `JsValue` conversion and a wasm-bindgen-test
+// exercising them. They wait on wasm-bindgen being available to the build; until then these
+// two string-in, string-out functions are all JS callers get.
+
+/// Like `config`, but returns the token set as a JSON object from language code to token
+/// array, in code order, for callers on the other side of a JSON boundary.
+pub fn config_json(v: Vec<String>) -> Result<String, Error> {
+    let config = config(v)?;
+    let sorted: BTreeMap<&String, &Vec<Token>> = config.iter().collect();
+    Ok(serde_json::to_string(&sorted)?)
+}
+
+/// Abbreviates `input` with the built-in (or registered) tokens of `lc`, loading them once
+/// per process via `config_shared`.
+pub fn abbreviate_for_language(input: &str, lc: &str) -> Result<String, Error> {
+    let config = config_shared(vec![lc.to_string()])?;
+    Ok(Tokenizer::for_language(lc, &config[lc]).tokenize(input))
+}
+
+/// Loads a combined JSON object from language code to token array, as written by
+/// `export_all`.
+pub fn config_from_export(json: &str) -> Result<HashMap<String, Vec<Token>>, Error> {
+    let parsed: HashMap<String, Vec<InToken>> = serde_json::from_str(json)?;
+    parsed.into_iter()
+        .map(|(lc, tokens)| {
+            let tokens = tokens.into_iter()
+                .map(|tk| Token::load(tk, &lc))
+                .collect::<Result<Vec<Token>, Error>>()?;
+            Ok((lc, tokens))
+        })
+        .collect()
+}
+
+pub fn to_csv(tokens: &[Token]) -> String {
+    let mut csv = String::from("canonical,full,type,preferFull,onlyCountries,note\n");
+    for tk in tokens {
+        let row = [
+            csv_field(&tk.canonical),
+            csv_field(tk.full.as_str()),
+            csv_field(tk.token_type.as_ref().map_or("", |t| t.as_str())),
+            tk.prefer_full.to_string(),
+            csv_field(&tk.only_countries.as_ref().map_or(String::new(), |c| c.join(","))),
+            csv_field(tk.note.as_deref().unwrap_or("")),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::token;
+
+    #[test]
+    fn test_config() {
+        let lcs = config(vec![String::from("de"), String::from("en")]).unwrap();
+        assert_eq!(lcs.len(), 2);
+        assert!(lcs.contains_key("de"));
+        assert!(lcs.contains_key("en"));
+
+        let empty_lc = config(Vec::new()).unwrap();
+        let every_lc = prepare(Tokens::codes(), false).unwrap();
+        assert_eq!(empty_lc.len(), every_lc.len());
+        for lc in Tokens::codes() {
+            assert!(empty_lc.contains_key(&lc));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "LanguageCodeNotSupported(\"zz\")")]
+    fn fail_config() {
+        config(vec![String::from("zz")]).unwrap();
+    }
+
+    #[test]
+    fn test_config_for_country() {
+        register_language("zy", r#"[
+            { "tokens": ["Street", "St"], "full": "Street", "canonical": "St" },
+            { "tokens": ["Road", "Rd"], "full": "Road", "canonical": "Rd" }
+        ]"#);
+        register_language("zy.US", r#"[
+            { "tokens": ["Road", "Rd", "Rod"], "full": "Road", "canonical": "Rd" },
+            { "tokens": ["Turnpike", "Tpke"], "full": "Turnpike", "canonical": "Tpke" }
+        ]"#);
+
+        let tokens = config_for_country("zy", "us").unwrap();
+        let fulls: Vec<&str> = tokens.iter().map(|tk| tk.full.as_str()).collect();
+        assert_eq!(fulls, vec!["Street", "Road", "Turnpike"]);
+        assert_eq!(tokens[1].tokens, vec!["Road", "Rd", "Rod"]);
+
+        assert_eq!(config_for_country("zy", "GB").unwrap().len(), 2);
+        let en = config_one("en").unwrap();
+        // Leaves out the groups `en.US.json` adds, see `test_country_overlay_file`.
+        let us: Vec<Token> = config_for_country("en", "US").unwrap().into_iter().filter(|tk| tk.language.as_deref() == Some("en")).collect();
+        let hk = config_for_country("en", "hk").unwrap();
+        let tagged = |tk: &Token, cc: &str| tk.only_countries.as_ref().is_some_and(|ccs| ccs.iter().any(|c| c == cc));
+        assert!(us.len() < en.len() && hk.len() < en.len());
+        assert!(us.iter().any(|tk| tagged(tk, "us")) && !us.iter().any(|tk| tagged(tk, "hk")));
+        assert!(hk.iter().any(|tk| tagged(tk, "hk")) && !hk.iter().any(|tk| tagged(tk, "us")));
+        assert_eq!(us.iter().filter(|tk| tk.only_countries.is_none()).count(), en.iter().filter(|tk| tk.only_countries.is_none()).count());
+        assert!(config_for_country("zz", "US").is_err());
+        assert!(!Tokens::codes().iter().any(|lc| lc.contains('.')));
+    }
+
+    #[test]
+    fn test_country_overlay_file() {
+        let township = |tokens: &[Token]| tokens.iter().any(|tk| tk.full.as_str() == "Township");
+        assert!(!township(&config_one("en").unwrap()));
+        assert!(!township(&config_for_country("en", "GB").unwrap()));
+
+        let us = config_for_country("en", "us").unwrap();
+        assert!(township(&us));
+        assert_eq!(us.iter().find(|tk| tk.full.as_str() == "Township").unwrap().language.as_deref(), Some("en.US"));
+        assert_eq!(abbreviate("12 Rural Route 4, Hamilton Township", &us), "12 Rr 4, Hamilton Twp");
+
+        let map = config(vec![String::from("en-US"), String::from("en-GB")]).unwrap();
+        assert_eq!(abbreviate("Hamilton Township", &map["en-US"]), "Hamilton Twp");
+        assert_eq!(abbreviate("Hamilton Township", &map["en-GB"]), "Hamilton Township");
+    }
+
+    #[test]
+    fn test_warmup() {
+        let map = config(Vec::new()).unwrap();
+        assert!(warmup(&map) < Duration::from_secs(60));
+        assert!(warmup(&HashMap::new()) < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_prepare_report() {
+        register_language("zx", r##"[
+            { "tokens": ["Street", "St"], "full": "Street", "canonical": "St" },
+            { "tokens": ["", "(?<=\\d+ )Apt"], "full": "(?<=\\d+ )Apt", "canonical": "#", "regex": true }
+        ]"##);
+
+        let report = prepare_report(vec![String::from("zx"), String::from("en")]).unwrap();
+        assert_eq!(report.tokens["zx"].len(), 1);
+        assert_eq!(report.tokens["en"], config(vec![String::from("en")]).unwrap()["en"]);
+
+        assert_eq!(report.errors, vec![(String::from("zx"), String::from("(?<=\\d+ )Apt"), String::from("LookBehindNotConst"))]);
+        assert_eq!(report.filtered["zx"], vec![String::from("(?<=\\d+ )Apt")]);
+        assert!(report.filtered["en"].is_empty());
+
+        let report = prepare_report(vec![String::from("en-US"), String::from("zx-US")]).unwrap();
+        assert_eq!(report.tokens["en-US"], config(vec![String::from("en-US")]).unwrap()["en-US"]);
+        assert_eq!(report.errors, vec![(String::from("zx-US"), String::from("(?<=\\d+ )Apt"), String::from("LookBehindNotConst"))]);
+        assert!(prepare_report(vec![String::from("zz-US")]).is_err());
+
+        assert_eq!(config(vec![String::from("zx")]).err(), Some(Error::FancyRegexError(String::from("LookBehindNotConst"))));
+        assert!(prepare_report(vec![String::from("zz")]).is_err());
+    }
+
+    #[test]
+    fn test_prepare_report_errors() {
+        register_language("zw", r##"[
+            { "tokens": ["", "(Apt"], "full": "(Apt", "canonical": "#", "regex": true },
+            { "tokens": ["Street", "St"], "full": "Street", "canonical": "St" },
+            { "tokens": ["", "Unit\\"], "full": "Unit\\", "canonical": "#", "regex": true }
+        ]"##);
+
+        let report = prepare_report(vec![String::from("zw")]).unwrap();
+        assert_eq!(report.tokens["zw"].len(), 1);
+        assert_eq!(report.errors, vec![
+            (String::from("zw"), String::from("(Apt"), String::from("UnclosedOpenParen")),
+            (String::from("zw"), String::from("Unit\\"), String::from("TrailingBackslash")),
+        ]);
+        // Neither fails over a lookaround.
+        assert!(report.filtered["zw"].is_empty());
+        assert!(prepare_report(vec![String::from("en")]).unwrap().errors.is_empty());
+    }
+
+    #[test]
+    fn test_lookaround_fallback() {
+        register_language("zv", r##"[
+            { "tokens": ["Street", "St"], "full": "Street", "canonical": "St" },
+            { "tokens": ["Apartment", "Apt"], "full": "(?<=\\d+ )Apartment", "canonical": "Apt", "regex": true }
+        ]"##);
+        register_language("zu", r##"[
+            { "tokens": ["Apartment", "Apt"], "full": "(Apartment", "canonical": "Apt", "regex": true }
+        ]"##);
+        let options = ConfigOptions::new().languages(vec![String::from("zv")]);
+
+        assert_eq!(config_with(options.clone()).err(), Some(Error::FancyRegexError(String::from("LookBehindNotConst"))));
+        let map = config_with(options.lookaround_fallback(true)).unwrap();
+        let tk = &map["zv"][1];
+        assert_eq!(map["zv"].len(), 2);
+        assert!(!tk.regex);
+        assert_eq!(tk.full, Replacer::String(String::from("Apartment")));
+        assert_eq!(tk.tokens, vec!["Apartment", "Apt"]);
+        assert_eq!(abbreviate("12 Apartment 3 Main Street", &map["zv"]), "12 Apt 3 Main St");
+        assert_eq!(expand("12 Apt 3 Main St", &map["zv"]), "12 Apartment 3 Main Street");
+
+        // Without a literal left once the lookbehind is gone, `full` is the longest form.
+        register_language("zt", r##"[
+            { "tokens": ["", "(?<=\\d+ )Ap(?:artmen)?t\\.?", "Apt", "Apartment"], "full": "(?<=\\d+ )Ap(?:artmen)?t\\.?", "canonical": "Apt", "regex": true }
+        ]"##);
+        let options = ConfigOptions::new().languages(vec![String::from("zt")]).lookaround_fallback(true);
+        let map = config_with(options).unwrap();
+        assert_eq!(map["zt"][0].full, Replacer::String(String::from("Apartment")));
+        assert_eq!(map["zt"][0].tokens, vec!["", "Apt", "Apartment"]);
+        assert_eq!(expand("12 Apt 3", &map["zt"]), "12 Apartment 3");
+
+        assert_eq!(literal_pattern("(?<=\\d+ )St\\.(?! Francis)"), Some(String::from("St.")));
+        assert_eq!(literal_pattern("(?<![a-z(])Ave"), None);
+        assert_eq!(literal_pattern("(?<!\\))Ave"), Some(String::from("Ave")));
+        assert_eq!(literal_pattern("\\d+th"), None);
+
+        let options = ConfigOptions::new().languages(vec![String::from("zu")]).lookaround_fallback(true);
+        assert_eq!(config_with(options).err(), Some(Error::FancyRegexError(String::from("UnclosedOpenParen"))));
+        let options = ConfigOptions::new().languages(vec![String::from("en")]);
+        assert_eq!(config_with(options.clone().lookaround_fallback(true)).unwrap(), config_with(options).unwrap());
+    }
+
+    #[test]
+    fn test_lookaround_tokens() {
+        let json = r##"[
+            { "tokens": ["", "(?<=\\d )Apt(?= \\d)"], "full": "(?<=\\d )Apt(?= \\d)", "canonical": "#", "regex": true }
+        ]"##;
+        let (tokens, filtered) = parse_filtered("lookaround", json).unwrap();
+        assert!(filtered.is_empty());
+        assert_eq!(abbreviate("12 Apt 3", &tokens), "12 # 3");
+        assert_eq!(abbreviate("Main Apt 3", &tokens), "Main Apt 3");
+        assert_eq!(abbreviate("12 Apt B", &tokens), "12 Apt B");
+    }
+
+    #[test]
+    fn test_imports() {
+        let dir = std::env::temp_dir().join(format!("geocoder-abbreviations-imports-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.json"), r#"[{ "tokens": ["Ul", "Ulica"], "full": "Ulica", "canonical": "Ul" }]"#).unwrap();
+        fs::write(dir.join("a.json"), r#"{
+            "imports": ["b"],
+            "tokens": [{ "tokens": ["Bul", "Bulevar"], "full": "Bulevar", "canonical": "Bul" }]
+        }"#).unwrap();
+
+        let map = config_with_overrides(&dir, vec![String::from("a"), String::from("b")]).unwrap();
+        let fulls: Vec<&str> = map["a"].iter().map(|tk| tk.full.as_str()).collect();
+        assert_eq!(fulls, vec!["Ulica", "Bulevar"]);
+        assert_eq!(map["b"].len(), 1);
+
+        fs::write(dir.join("b.json"), r#"{ "imports": ["a"], "tokens": [] }"#).unwrap();
+        assert!(matches!(prepare_dir(&dir), Err(Error::ImportCycle(_))));
+        assert_eq!(config_with_overrides(&dir, vec![String::from("a")]).err(), Some(Error::ImportCycle(String::from("a -> b -> a"))));
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        // The files in `tokens/` are shared with the JS package, which only reads lists of groups.
+        for entry in fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tokens")).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let json = fs::read_to_string(&path).unwrap();
+                assert!(serde_json::from_str::<Vec<InToken>>(&json).is_ok(), "{}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn test_config_shared() {
+        let first = config_shared(vec![String::from("en"), String::from("de")]).unwrap();
+        let second = config_shared(vec![String::from("en")]).unwrap();
+
+        assert!(Arc::ptr_eq(&first["en"], &second["en"]));
+        assert_eq!(*first["de"], config(vec![String::from("de")]).unwrap()["de"]);
+        assert_eq!(config_shared(Vec::new()).unwrap().len(), Tokens::codes().len());
+        assert!(config_shared(vec![String::from("zz")]).is_err());
+    }
+
+    #[test]
+    fn test_config_cache() {
+        let regexes = |map: &HashMap<String, Vec<Token>>| -> Vec<Arc<Regex>> {
+            map["en"].iter().filter_map(|tk| match &tk.full {
+                Replacer::Regex { regex, .. } => Some(regex.clone()),
+                Replacer::String(_) => None
+            }).collect()
+        };
+        let first = config(vec![String::from("en")]).unwrap();
+        let second = config(vec![String::from("en")]).unwrap();
+        assert_eq!(first, second);
+        assert!(!regexes(&first).is_empty());
+        for (a, b) in regexes(&first).iter().zip(&regexes(&second)) {
+            assert!(Arc::ptr_eq(a, b));
+        }
+
+        // Re-registering a language drops what was cached for it, region tags included.
+        register_language("zs", r#"[{ "tokens": ["St", "Street"], "full": "Street", "canonical": "St" }]"#);
+        assert_eq!(config_one("zs").unwrap()[0].canonical, "St");
+        assert_eq!(config(vec![String::from("zs"), String::from("zs-US")]).unwrap()["zs-US"][0].canonical, "St");
+        register_language("zs", r#"[{ "tokens": ["Str", "Street"], "full": "Street", "canonical": "Str" }]"#);
+        let map = config(vec![String::from("zs"), String::from("zs-US")]).unwrap();
+        assert_eq!(map["zs"][0].canonical, "Str");
+        assert_eq!(map["zs-US"][0].canonical, "Str");
+    }
+
+    #[test]
+    fn test_config_one() {
+        assert_eq!(config_one("en").unwrap(), config(vec![String::from("en")]).unwrap()["en"]);
+        assert_eq!(config_one("zz"), Err(Error::LanguageCodeNotSupported(String::from("zz"))));
+    }
+
+    #[test]
+    fn test_config_typed_only() {
+        let typed = config_typed_only(vec![String::from("en")]).unwrap();
+        let every = config(vec![String::from("en")]).unwrap();
+        assert!(!typed["en"].is_empty());
+        assert!(typed["en"].len() < every["en"].len());
+        assert!(typed["en"].iter().all(|tk| tk.token_type.is_some()));
+    }
+
+    #[test]
+    fn test_configs_equal_unordered() {
+        let a = config(vec![String::from("de"), String::from("en")]).unwrap();
+        let mut b = config(vec![String::from("de"), String::from("en")]).unwrap();
+        assert!(configs_equal_unordered(&a, &b));
+
+        b.get_mut("en").unwrap().reverse();
+        assert_ne!(a["en"], b["en"]);
+        assert!(configs_equal_unordered(&a, &b));
+
+        b.get_mut("en").unwrap().pop();
+        assert!(!configs_equal_unordered(&a, &b));
+
+        let c = config(vec![String::from("de"), String::from("fr")]).unwrap();
+        assert!(!configs_equal_unordered(&a, &c));
+    }
+
+    #[test]
+    fn test_config_with_env() {
+        let dir = std::env::temp_dir().join(format!("geocoder-abbreviations-env-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("en.json"), r#"[{ "tokens": ["Foo", "F"], "full": "Foo", "canonical": "F" }]"#).unwrap();
+        fs::write(dir.join("xx.json"), r#"[{ "tokens": ["Bar", "B"], "full": "Bar", "canonical": "B" }]"#).unwrap();
+
+        let builtin = config_with_dir(None, vec![String::from("en"), String::from("de")]).unwrap();
+        assert!(builtin["en"].len() > 1);
+        assert!(config_with_dir(None, vec![String::from("xx")]).is_err());
+
+        let layered = config_with_dir(Some(dir.as_os_str()), vec![String::from("en"), String::from("de")]).unwrap();
+        assert_eq!(layered["en"].len(), 1);
+        assert_eq!(layered["en"][0].canonical, "F");
+        assert_eq!(layered["de"].len(), builtin["de"].len());
+
+        let every = config_with_overrides(&dir, Vec::new()).unwrap();
+        assert_eq!(every.len(), Tokens::codes().len() + 1);
+        assert_eq!(every["xx"][0].canonical, "B");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_register_language() {
+        assert_eq!(config(vec![String::from("qq")]).err(), Some(Error::LanguageCodeNotSupported(String::from("qq"))));
+
+        register_language("qq", r#"[{ "tokens": ["Foo", "F"], "full": "Foo", "canonical": "F" }]"#);
+        let map = config(vec![String::from("qq"), String::from("en")]).unwrap();
+        assert_eq!(map["qq"].len(), 1);
+        assert_eq!(map["qq"][0].canonical, "F");
+        assert!(!config(Vec::new()).unwrap().contains_key("qq"));
+
+        // `config_one` doesn't go through the shared cache, which other tests compare across calls.
+        let en = config_one("en").unwrap();
+        register_language("en", r#"[{ "tokens": ["Foo", "F"], "full": "Foo", "canonical": "F" }]"#);
+        assert_eq!(config_one("en").unwrap(), en);
+        assert!(!registered().read().unwrap().contains_key("en"));
+    }
+
+    #[test]
+    fn test_config_from_dir() {
+        let dir = std::env::temp_dir().join(format!("geocoder-abbreviations-dir-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("en.json"), r#"[{ "tokens": ["Pkwy", "Parkway"], "full": "Parkway", "canonical": "Pkwy" }]"#).unwrap();
+        fs::write(dir.join("local.json"), r#"[{ "tokens": ["Gdns", "Gardens"], "full": "Gardens", "canonical": "Gdns" }]"#).unwrap();
+
+        let map = config_from_dir(&dir, vec![String::from("en")]).unwrap();
+        assert_eq!(map.len(), 1);
+        assert_eq!(map["en"].len(), 1);
+        assert_eq!(config_from_dir(&dir, Vec::new()).unwrap().len(), 2);
+        assert_eq!(config_from_dir(&dir, vec![String::from("de")]).err(), Some(Error::TokenFileImportNotSupported(String::from("de"))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_utf16_token_files() {
+        let dir = std::env::temp_dir().join(format!("geocoder-abbreviations-utf16-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let json = r#"[{ "tokens": ["Straße", "Str"], "full": "Straße", "canonical": "Str" }]"#;
+
+        let mut le = vec![0xFF, 0xFE];
+        le.extend(json.encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+        fs::write(dir.join("le.json"), le).unwrap();
+        let mut be = vec![0xFE, 0xFF];
+        be.extend(json.encode_utf16().flat_map(|unit| unit.to_be_bytes()));
+        fs::write(dir.join("be.json"), be).unwrap();
+        let mut bom = vec![0xEF, 0xBB, 0xBF];
+        bom.extend(json.as_bytes());
+        fs::write(dir.join("bom.json"), bom).unwrap();
+
+        let map = config_with_overrides(&dir, vec![String::from("le"), String::from("be"), String::from("bom")]).unwrap();
+        for lc in &["le", "be", "bom"] {
+            assert_eq!(map[*lc][0].full.as_str(), "Straße");
+        }
+
+        fs::write(dir.join("bad.json"), [0xFF, 0xFE, 0x00]).unwrap();
+        assert_eq!(prepare_dir(&dir).err(), Some(Error::TokenFileImportNotSupported(String::from("bad"))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_all() {
+        let export = export_all().unwrap();
+        assert_eq!(export, export_all().unwrap());
+        assert_eq!(config_from_export(&export).unwrap(), config(Vec::new()).unwrap());
+
+        let positions: Vec<usize> = Tokens::codes().iter().map(|lc| export.find(&format!("\"{}\":[", lc)).unwrap()).collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        assert!(matches!(config_from_export("[]"), Err(Error::JsonParse(_))));
+    }
+
+    #[test]
+    fn test_config_with_custom() {
+        let extra = parse("custom", r#"[{ "tokens": ["Bvd", "Boulevardo"], "full": "Boulevardo", "canonical": "Bvd" }]"#).unwrap();
+        let en = config_one("en").unwrap();
+        let tokens = config_with_custom("en", extra).unwrap();
+
+        assert_eq!(tokens.len(), en.len() + 1);
+        assert_eq!(tokens[..en.len()], en[..]);
+        assert_eq!(abbreviate("Main Street Boulevardo", &tokens), "Main St Bvd");
+        assert_eq!(abbreviate("Main Street Boulevardo", &en), "Main St Boulevardo");
+        assert!(config_with_custom("zz", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_json_boundary() {
+        let json = config_json(vec![String::from("de")]).unwrap();
+        let parsed = config_from_export(&json).unwrap();
+        assert_eq!(parsed, config(vec![String::from("de")]).unwrap());
+        assert_eq!(config_json(vec![String::from("zz")]).unwrap_err(), Error::LanguageCodeNotSupported(String::from("zz")));
+
+        assert_eq!(abbreviate_for_language("Main Street", "en").unwrap(), "Main St");
+        assert!(abbreviate_for_language("Main Street", "zz").is_err());
+    }
+
+    #[test]
+    fn test_token_id() {
+        let en = config_one("en").unwrap();
+        let again = config_one("en").unwrap();
+        for (a, b) in en.iter().zip(&again) {
+            assert_eq!(a.id(), b.id());
+        }
+        assert_eq!(en[0].id().len(), 16);
+
+        // Ids are equal exactly when canonical, full and language are.
+        let de = config_one("de").unwrap();
+        let mut by_key = HashMap::new();
+        for tk in en.iter().chain(&de) {
+            by_key.insert((&tk.canonical, tk.full.as_str(), &tk.language), tk.id());
+        }
+        let ids: std::collections::HashSet<&String> = by_key.values().collect();
+        assert_eq!(ids.len(), by_key.len());
+        assert!(by_key.len() < en.len() + de.len());
+
+        let tk = |lc: &str| parse(lc, r#"[{ "tokens": ["St", "Street"], "full": "Street", "canonical": "St" }]"#).unwrap().remove(0);
+        assert_eq!(tk("en").id(), tk("en").id());
+        assert_ne!(tk("en").id(), tk("sv").id());
+    }
+
+    #[test]
+    fn test_serialize_token() {
+        let en = config_one("en").unwrap();
+        let tk = en.iter().find(|tk| tk.only_countries.is_some() && tk.regex).unwrap();
+        let json = serde_json::to_value(tk).unwrap();
+        assert_eq!(json["full"], tk.full.as_str());
+        assert_eq!(json["regex"], true);
+        assert_eq!(json["onlyCountries"], serde_json::json!(tk.only_countries));
+        assert_eq!(json["onlyLayers"], serde_json::json!(["address"]));
+        assert_eq!(json["spanBoundaries"], 1);
+        assert!(json.get("preferFull").is_none());
+        assert!(json.get("only_countries").is_none());
+
+        let round_trip = parse("en", &serde_json::to_string(&en).unwrap()).unwrap();
+        assert_eq!(round_trip, en);
+
+        let street = en.iter().find(|tk| tk.full.as_str() == "Street").unwrap();
+        assert_eq!(serde_json::to_value(street).unwrap()["type"], "way");
+    }
+
+    #[test]
+    fn test_deserialize_token() {
+        let en = config_one("en").unwrap();
+        let tokens: Vec<Token> = serde_json::from_str(&Tokens::import("en").unwrap()).unwrap();
+        assert_eq!(tokens.len(), en.len());
+        assert!(tokens.iter().all(|tk| tk.language.is_none()));
+        for (tk, loaded) in tokens.iter().zip(&en) {
+            assert_eq!(*tk, Token { language: None, ..loaded.clone() });
+        }
+
+        let round_trip: Vec<Token> = serde_json::from_str(&serde_json::to_string(&tokens).unwrap()).unwrap();
+        assert_eq!(round_trip, tokens);
+
+        let tk: Token = serde_json::from_str(r#"{ "tokens": ["", "(\\d+)th"], "full": "(\\d+)th", "canonical": "$1", "regex": true, "type": "ordinal" }"#).unwrap();
+        assert!(matches!(tk.full, Replacer::Regex { .. }));
+        assert_eq!(tk.token_type, Some(TokenType::Ordinal));
+        assert_eq!(abbreviate("5th", &[tk]), "5");
+
+        let bad = serde_json::from_str::<Token>(r#"{ "tokens": ["Av"], "full": "Avenue", "canonical": "Av", "type": "road" }"#);
+        assert!(bad.is_err());
+        let bad = serde_json::from_str::<Token>(r#"{ "tokens": [""], "full": "(a", "canonical": "", "regex": true }"#);
+        assert!(bad.is_err());
+    }
+
+    #[test]
+    fn test_token_type_strings() {
+        let types = [
+            TokenType::PostalBox,
+            TokenType::Cardinal,
+            TokenType::Number,
+            TokenType::Ordinal,
+            TokenType::Unit,
+            TokenType::Way,
+            TokenType::Determiner,
+        ];
+        for t in types {
+            assert_eq!(TokenType::from_str(t.as_str()), Ok(t));
+            assert_eq!(t.to_string().parse::<TokenType>(), Ok(t));
+        }
+        assert_eq!(TokenType::PostalBox.to_string(), "box");
+        assert_eq!("Box".parse::<TokenType>(), Err(Error::TokenTypeNotSupported(String::from("Box"))));
+    }
+
+    #[test]
+    fn test_error_display() {
+        assert_eq!(Error::LanguageCodeNotSupported(String::from("zz")).to_string(), "language code not supported: zz");
+        assert_eq!(Error::TokenFileImportNotSupported(String::from("zz")).to_string(), "unable to import token file: zz");
+        assert_eq!(Error::TokenTypeNotSupported(String::from("road")).to_string(), "token type not supported: road");
+        assert_eq!(Error::FancyRegexError(String::from("UnclosedOpenParen")).to_string(), "invalid regex in token file: UnclosedOpenParen");
+        assert_eq!(Error::JsonParse(String::from("EOF")).to_string(), "unable to parse token JSON: EOF");
+        assert_eq!(Error::ImportCycle(String::from("a -> a")).to_string(), "token file imports itself: a -> a");
+        assert_eq!(Error::RegionConflict(String::from("en-GB")).to_string(), "language tag region conflicts with the country: en-GB");
+
+        let boxed: Box<dyn std::error::Error> = Box::new(Error::FancyRegexError(String::from("UnclosedOpenParen")));
+        assert_eq!(boxed.to_string(), "invalid regex in token file: UnclosedOpenParen");
+    }
+
+    #[test]
+    fn test_json_parse_error() {
+        let dir = std::env::temp_dir().join(format!("geocoder-abbreviations-json-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("broken.json"), r#"[{ "tokens": ["St", "Street"], "full": "Street" "#).unwrap();
+
+        assert!(matches!(prepare_dir(&dir), Err(Error::JsonParse(_))));
+        assert!(matches!(parse("broken", r#"[{ "tokens": [] }]"#), Err(Error::JsonParse(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_with() {
+        assert_eq!(ConfigOptions::new().build(), ConfigOptions::default());
+        assert_eq!(config_with(ConfigOptions::default()).unwrap(), config(Vec::new()).unwrap());
+
+        let lcs = vec![String::from("de"), String::from("en")];
+        let options = ConfigOptions::new().languages(lcs.clone()).build();
+        assert_eq!(config_with(options).unwrap(), config(lcs.clone()).unwrap());
+
+        let options = ConfigOptions::new().languages(lcs.clone()).country("US").include_global(true).build();
+        assert_eq!(options.country.as_deref(), Some("US"));
+        let map = config_with(options).unwrap();
+        let mut us = config_for_country("en", "US").unwrap();
+        us.extend(parse(GLOBAL, &Tokens::import(GLOBAL).unwrap()).unwrap());
+        assert_eq!(map["en"], us);
+
+        let unknown = ConfigOptions::new().languages(vec![String::from("zz")]).build();
+        assert_eq!(config_with(unknown).unwrap_err(), Error::LanguageCodeNotSupported(String::from("zz")));
+    }
+
+    #[test]
+    fn test_layers() {
+        let tokens = parse("zz", r#"[
+            { "tokens": ["Pl", "Place"], "full": "Place", "canonical": "Pl", "onlyLayers": ["address", "place", "region"] }
+        ]"#).unwrap();
+        assert_eq!(tokens[0].only_layers, Some(vec![Layer::Address, Layer::Place, Layer::Region]));
+        assert_eq!(serde_json::to_value(&tokens[0]).unwrap()["onlyLayers"], serde_json::json!(["address", "place", "region"]));
+
+        let unknown = parse("zz", r#"[{ "tokens": ["Pl"], "full": "Pl", "canonical": "Pl", "onlyLayers": ["street"] }]"#);
+        assert_eq!(unknown.unwrap_err(), Error::LayerNotSupported(String::from("street")));
+        assert_eq!(Error::LayerNotSupported(String::from("street")).to_string(), "layer not supported: street");
+        assert_eq!("poi".parse::<Layer>(), Ok(Layer::Poi));
+
+        let en = config_one("en").unwrap();
+        let options = ConfigOptions::new().languages(vec![String::from("en")]).layer(Layer::Place).build();
+        let place = config_with(options).unwrap().remove("en").unwrap();
+        assert!(place.len() < en.len());
+        assert!(place.iter().all(|tk| tk.only_layers.is_none()));
+        let options = ConfigOptions::new().languages(vec![String::from("en")]).layer(Layer::Address).build();
+        assert_eq!(config_with(options).unwrap()["en"], en);
+    }
+
+    #[test]
+    fn test_token_types_option() {
+        let lcs = vec![String::from("de"), String::from("en"), String::from("fr")];
+        let options = ConfigOptions::new().languages(lcs.clone()).token_types(vec![TokenType::Number]).build();
+        let map = config_with(options).unwrap();
+        assert_eq!(map.len(), 3);
+        assert!(!map["en"].is_empty() && !map["fr"].is_empty());
+        assert!(map["de"].is_empty());
+        assert!(map.values().flatten().all(|tk| tk.token_type == Some(TokenType::Number)));
+
+        let options = ConfigOptions::new().languages(lcs).token_types(vec![TokenType::Way, TokenType::PostalBox]).build();
+        let map = config_with(options).unwrap();
+        assert!(map["en"].iter().any(|tk| tk.token_type == Some(TokenType::PostalBox)));
+        assert!(map.values().flatten().all(|tk| matches!(tk.token_type, Some(TokenType::Way) | Some(TokenType::PostalBox))));
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let options = ConfigOptions::new().languages(vec![String::from("en")]).case_insensitive(true).build();
+        let map = config_with(options).unwrap();
+        let tokenizer = Tokenizer::new(&map["en"]);
+        for input in ["Main AVE", "Main ave", "Main Ave"] {
+            assert_eq!(tokenizer.tokenize(input), "Main Av");
+        }
+        assert_eq!(tokenizer.tokenize("NORTH MAIN STREET"), "N MAIN St");
+        assert!(map["en"].iter().all(|tk| tk.case_insensitive));
+
+        let plain = config(vec![String::from("en")]).unwrap();
+        assert_eq!(Tokenizer::new(&plain["en"]).tokenize("Main AVE"), "Main AVE");
+
+        let mut tk = parse("zz", r#"[{ "tokens": ["", "(?P<n>\\d+)th"], "full": "(?P<n>\\d+)th", "canonical": "${n}", "regex": true }]"#).unwrap().remove(0);
+        tk.make_case_insensitive().unwrap();
+        assert_eq!(abbreviate("5TH Avenue", &[tk]), "5 Avenue");
+    }
+
+    #[test]
+    fn test_case_insensitive_round_trip() {
+        let options = ConfigOptions::new().languages(vec![String::from("en")]).case_insensitive(true).build();
+        let map = config_with(options).unwrap();
+
+        let json = serde_json::to_string(&map["en"]).unwrap();
+        assert!(json.contains(r#""caseInsensitive":true"#));
+        let tokens: Vec<Token> = serde_json::from_str(&json).unwrap();
+        assert!(tokens.iter().all(|tk| tk.case_insensitive));
+        assert_eq!(abbreviate("Main AVE", &tokens), "Main Av");
+        assert_eq!(abbreviate("5TH Avenue", &tokens), abbreviate("5TH Avenue", &map["en"]));
+
+        let plain = serde_json::to_string(&config(vec![String::from("en")]).unwrap()["en"]).unwrap();
+        assert!(!plain.contains("caseInsensitive"));
+    }
+
+    #[test]
+    fn test_config_with_options() {
+        let global = parse(GLOBAL, &Tokens::import(GLOBAL).unwrap()).unwrap();
+        let number = global.iter().find(|tk| tk.token_type == Some(TokenType::Number)).unwrap();
+        let lcs = vec![String::from("de"), String::from("en")];
+
+        let options = ConfigOptions::new().languages(lcs);
+        let merged = config_with(options.clone().include_global(true).build()).unwrap();
+        let plain = config_with(options.clone().build()).unwrap();
+        for lc in &["de", "en"] {
+            assert_eq!(merged[*lc].len(), plain[*lc].len() + global.len());
+            assert!(merged[*lc].contains(number));
+            assert!(!plain[*lc].contains(number));
+            assert_eq!(merged[*lc][..plain[*lc].len()], plain[*lc][..]);
+        }
+        assert!(!Tokens::codes().contains(&String::from(GLOBAL)));
+        assert_eq!(config(vec![String::from(GLOBAL)]).unwrap_err(), Error::LanguageCodeNotSupported(String::from(GLOBAL)));
+
+        #[allow(deprecated)]
+        let wrapped = config_with_options(options.languages.clone(), true).unwrap();
+        assert_eq!(wrapped, merged);
+    }
+
+    #[cfg(not(feature = "embedded-tokens"))]
+    #[test]
+    fn test_runtime_token_files() {
+        let dir = std::env::temp_dir().join(format!("geocoder-abbreviations-runtime-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("zr.json"), r#"[{ "tokens": ["Gt", "Gate"], "full": "Gate", "canonical": "Gt" }]"#).unwrap();
+
+        assert_eq!(Tokens::files_in(&dir), vec![String::from("zr.json")]);
+        let json = String::from_utf8(Tokens::read_in(&dir, "zr.json").unwrap()).unwrap();
+        assert_eq!(abbreviate("Kings Gate", &parse("zr", &json).unwrap()), "Kings Gt");
+        assert!(Tokens::read_in(&dir, "en.json").is_none());
+
+        assert!(Tokens::codes().contains(&String::from("en")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_all_lcs() {
+        let mut fs_lcs = read_files();
+        alphanumeric_sort::sort_str_slice(&mut fs_lcs);
+        assert_eq!(Tokens::codes(), fs_lcs);
+    }
+
+    #[test]
+    fn test_supported_languages() {
+        let mut fs_lcs = read_files();
+        fs_lcs.sort();
+        assert_eq!(supported_languages(), fs_lcs);
+        for lc in supported_languages() {
+            assert!(config_one(&lc).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_prepare() {
+        let lcs = prepare(vec![String::from("de"), String::from("en")], false).unwrap();
+        assert_eq!(lcs.len(), 2);
+        assert!(lcs.contains_key("de"));
+        assert!(lcs.contains_key("en"));
+    }
+
+    #[test]
+    #[should_panic(expected = "TokenFileImportNotSupported(\"zz\")")]
+    fn fail_import() {
+        Tokens::import("zz").unwrap();
+    }
+
+    #[test]
+    fn test_token_values() {
+        let map = config(Vec::new()).unwrap();
+
+        for lc in map.values() {
+            for tk in lc {
+                assert!(!tk.tokens.is_empty());
+                if let Some(l) = &tk.only_layers {
+                    assert_eq!(l, &vec![Layer::Address]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_preferred_form() {
+        let parsed: Vec<InToken> = serde_json::from_str(r#"[
+            { "tokens": ["St", "Street"], "full": "Street", "canonical": "St" },
+            { "tokens": ["Coll", "College"], "full": "College", "canonical": "Coll", "preferFull": true }
+        ]"#).unwrap();
+        let tokens: Vec<Token> = parsed.into_iter().map(|tk| Token::new(tk).unwrap()).collect();
+
+        assert_eq!(tokens[0].preferred_form(Direction::Abbreviate), "St");
+        assert_eq!(tokens[0].preferred_form(Direction::Expand), "Street");
+        assert_eq!(tokens[1].preferred_form(Direction::Abbreviate), "College");
+        assert_eq!(tokens[1].preferred_form(Direction::Expand), "College");
+    }
+
+    #[test]
+    fn test_words() {
+        let tk = token(r#"{ "tokens": ["PO Box", "Post Office Box"], "full": "Post Office Box", "canonical": "PO Box", "type": "box" }"#);
+        assert_eq!(tk.word_count(), 3);
+        assert_eq!(tk.words(), vec!["Post", "Office", "Box"]);
+    }
+
+    #[test]
+    fn test_coalesce_by_canonical() {
+        let tokens = parse("xx", r#"[
+            { "tokens": ["Cl", "Close"], "full": "Close", "canonical": "Cl" },
+            { "tokens": ["St", "Street"], "full": "Street", "canonical": "St" },
+            { "tokens": ["Cl", "Clinic"], "full": "Clinic", "canonical": "Cl", "preferFull": true },
+            { "tokens": ["", "a+"], "full": "a+", "canonical": "Cl", "regex": true }
+        ]"#).unwrap();
+
+        let coalesced = coalesce_by_canonical(tokens);
+        assert_eq!(coalesced.len(), 3);
+        assert_eq!(coalesced[0].tokens, vec!["Cl", "Close", "Clinic"]);
+        assert_eq!(coalesced[0].full.as_str(), "Close");
+        assert!(!coalesced[0].prefer_full);
+        assert_eq!(coalesced[1].canonical, "St");
+        assert!(coalesced[2].regex);
+    }
+
+    #[test]
+    fn test_order_by_frequency() {
+        let tokens = config(vec![String::from("en")]).unwrap().remove("en").unwrap();
+        let len = tokens.len();
+        let mut freq = HashMap::new();
+        freq.insert(String::from("Rd"), 10);
+        freq.insert(String::from("St"), 50);
+
+        let ordered = order_by_frequency(tokens, &freq);
+        assert_eq!(ordered.len(), len);
+        let canonicals: Vec<&str> = ordered.iter().map(|tk| tk.canonical.as_str()).collect();
+        let last_st = canonicals.iter().rposition(|c| *c == "St").unwrap();
+        let first_rd = canonicals.iter().position(|c| *c == "Rd").unwrap();
+        let last_rd = canonicals.iter().rposition(|c| *c == "Rd").unwrap();
+        assert_eq!(canonicals[0], "St");
+        assert!(last_st < first_rd);
+        assert!(canonicals[last_rd + 1..].iter().all(|c| *c != "St" && *c != "Rd"));
+    }
+
+    #[test]
+    fn test_token_at() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let matches = Tokenizer::new(&map["en"]).find_tokens("Main Street");
+
+        assert_eq!(token_at(&map["en"], matches[0].token_index).unwrap().full.as_str(), "Street");
+        assert_eq!(token_at(&map["en"], map["en"].len()), None);
+    }
+
+    #[test]
+    fn test_index() {
+        let en = config_one("en").unwrap();
+        let index = index(&en);
+
+        let st: Vec<&str> = index["St"].iter().map(|&i| en[i].full.as_str()).collect();
+        assert!(st.len() > 1);
+        assert!(st.contains(&"Street") && st.contains(&"Saint"));
+        assert!(index["Street"].iter().all(|&i| en[i].tokens.contains(&String::from("Street"))));
+        assert!(!index.contains_key(""));
+        assert!(index.values().all(|indices| indices.windows(2).all(|w| w[0] < w[1])));
+    }
+
+    #[test]
+    fn test_clone_shares_regexes() {
+        let en = config_one("en").unwrap();
+        let cloned = en.clone();
+
+        assert_eq!(cloned, en);
+        for (a, b) in en.iter().zip(&cloned) {
+            if let (Replacer::Regex { regex: a, .. }, Replacer::Regex { regex: b, .. }) = (&a.full, &b.full) {
+                assert!(Arc::ptr_eq(a, b));
+            }
+        }
+        assert!(en.iter().any(|tk| tk.regex));
+        for input in &["Main Street", "1st Avenue Northwest", "Post Office Box 12"] {
+            assert_eq!(abbreviate(input, &cloned), abbreviate(input, &en));
+        }
+    }
+
+    #[test]
+    fn test_all_tokens() {
+        let map = config(Vec::new()).unwrap();
+        assert_eq!(all_tokens(&map).count(), map.values().map(Vec::len).sum::<usize>());
+        assert!(all_tokens(&map).all(|(lc, tk)| map[lc].contains(tk)));
+
+        let lcs: Vec<&str> = all_tokens(&map).map(|(lc, _)| lc).collect();
+        assert!(lcs.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(all_tokens(&HashMap::new()).count(), 0);
+    }
+
+    #[test]
+    fn test_region_tags() {
+        let map = config(vec![String::from("en-GB"), String::from("pt-BR"), String::from("en")]).unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map["en-GB"], config_for_country("en", "GB").unwrap());
+        assert_eq!(map["pt-BR"], config_for_country("pt", "BR").unwrap());
+        assert_eq!(map["en"], config_one("en").unwrap());
+        assert!(map["en-GB"].iter().all(|tk| tk.language.as_deref() == Some("en")));
+
+        assert_eq!(config_one("en_US").unwrap(), config_for_country("en", "US").unwrap());
+        assert_eq!(config_one("sr-Latn-RS").unwrap(), config_for_country("sr", "RS").unwrap());
+        assert_eq!(config_one("en-Latn").unwrap(), config_one("en").unwrap());
+        assert_eq!(config(vec![String::from("zz-ZZ")]).err(), Some(Error::LanguageCodeNotSupported(String::from("zz-ZZ"))));
+        assert!(config_one("zz-ZZ").is_err());
+
+        // Scoping a region tag to its own country again leaves it as it was.
+        let options = ConfigOptions::new().languages(vec![String::from("en-US")]).country("US");
+        let map = config_with(options).unwrap();
+        assert_eq!(map["en-US"], config_one("en-US").unwrap());
+        for full in ["Rural Route", "Township"] {
+            assert_eq!(map["en-US"].iter().filter(|tk| tk.full.as_str() == full).count(), 1, "{}", full);
+        }
+        assert_eq!(config_for_country("en-us", "US").unwrap(), config_one("en-US").unwrap());
+        let options = ConfigOptions::new().languages(vec![String::from("en-GB")]).country("US");
+        assert_eq!(config_with(options).err(), Some(Error::RegionConflict(String::from("en-GB"))));
+        assert_eq!(config_for_country("en-GB", "US").err(), Some(Error::RegionConflict(String::from("en-GB"))));
+    }
+
+    #[test]
+    fn test_merge() {
+        let lcs = vec![String::from("en"), String::from("de")];
+        let en = config_one("en").unwrap();
+        let de = config_one("de").unwrap();
+        let merged = merge(config(lcs.clone()).unwrap(), &lcs);
+
+        assert_eq!(merged[..en.len()], en[..]);
+        assert!(merged[en.len()..].iter().all(|tk| tk.language.as_deref() == Some("de")));
+        let same = |a: &Token, b: &Token| Token { language: None, ..a.clone() } == Token { language: None, ..b.clone() };
+        for (i, tk) in merged.iter().enumerate() {
+            assert!(!merged[..i].iter().any(|earlier| same(earlier, tk)));
+        }
+        assert!(de.iter().all(|tk| merged.iter().any(|m| same(m, tk))));
+
+        let tags = vec![String::from("en-GB"), String::from("en")];
+        let gb = config_one("en-GB").unwrap();
+        let overlapping = merge(config(tags.clone()).unwrap(), &tags);
+        assert_eq!(overlapping[..gb.len()], gb[..]);
+        assert_eq!(overlapping.len(), gb.len() + en.iter().filter(|tk| !gb.contains(tk)).count());
+
+        let reversed = merge(config(lcs.clone()).unwrap(), &[String::from("de")]);
+        assert_eq!(reversed[..de.len()], de[..]);
+        assert_eq!(reversed.len(), merged.len());
+    }
+
+    #[test]
+    // The compiled regex caches internally, but hashing only looks at its pattern.
+    #[allow(clippy::mutable_key_type)]
+    fn test_token_hash() {
+        let json = r#"[
+            { "tokens": ["St", "Street"], "full": "Street", "canonical": "St" },
+            { "tokens": ["", "(\\d+)(?:st|nd)"], "full": "(\\d+)(?:st|nd)", "canonical": "$1", "regex": true }
+        ]"#;
+        let mut set = std::collections::HashSet::new();
+        for tk in parse("en", json).unwrap().into_iter().chain(parse("en", json).unwrap()) {
+            set.insert(tk);
+        }
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&parse("en", json).unwrap()[1]));
+        assert!(!set.contains(&parse("de", json).unwrap()[0]));
+
+        let en = config_one("en").unwrap();
+        let unique: std::collections::HashSet<&Token> = en.iter().collect();
+        assert_eq!(unique.len(), en.len());
+    }
+
+    #[test]
+    fn test_languages_for_surface() {
+        let map = config(Vec::new()).unwrap();
+
+        assert_eq!(languages_for_surface(&map, "Av"), vec!["en", "es", "fr", "pt"]);
+        assert_eq!(languages_for_surface(&map, "str"), vec!["nl", "ro"]);
+        assert_eq!(languages_for_surface(&map, "Str"), vec!["de"]);
+        assert!(languages_for_surface(&map, "Springfield").is_empty());
+    }
+
+    #[test]
+    fn test_find() {
+        let en = config_one("en").unwrap();
+
+        let st = find(&en, "St");
+        assert!(st.len() > 1);
+        assert!(st.iter().any(|tk| tk.full.as_str() == "Street") && st.iter().any(|tk| tk.full.as_str() == "Saint"));
+        assert!(find(&en, "st").is_empty());
+        assert_eq!(find_ci(&en, "st"), st);
+        assert_eq!(find_ci(&en, "BLVD"), find(&en, "Blvd"));
+        assert!(!find(&en, "Blvd").is_empty());
+        assert!(find_ci(&en, "Springfield").is_empty());
+    }
+
+    #[test]
+    fn test_regex_literal_partition() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let en = &map["en"];
+
+        let regex = regex_tokens(en);
+        let literal = literal_tokens(en);
+        assert!(!regex.is_empty());
+        assert!(!literal.is_empty());
+        assert_eq!(regex.len() + literal.len(), en.len());
+
+        let raw = fs::read_to_string("./tokens/en.json").unwrap();
+        assert_eq!(regex.len(), raw.matches("\"regex\": true").count());
+
+        assert!(regex.iter().all(|tk| tk.regex));
+        assert!(literal.iter().all(|tk| !tk.regex));
+    }
+
+    #[test]
+    fn test_geo_restricted_tokens() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let restricted = geo_restricted_tokens(&map["en"]);
+        assert!(!restricted.is_empty());
+        assert!(restricted.len() < map["en"].len());
+        assert!(restricted.iter().all(|tk| tk.only_countries.is_some()));
+        assert!(restricted.iter().any(|tk| tk.only_countries.as_ref().unwrap().contains(&String::from("us"))));
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let csv = to_csv(&map["en"]);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("canonical,full,type,preferFull,onlyCountries,note"));
+        assert!(csv.lines().any(|line| line == "St,Street,way,false,,"));
+        assert_eq!(csv.lines().count(), map["en"].len() + 1);
+
+        let parsed: Vec<InToken> = serde_json::from_str(r#"[{
+            "tokens": ["Foo", "F"],
+            "full": "Foo",
+            "canonical": "F",
+            "note": "a \"quoted\", comma note",
+            "onlyCountries": ["us", "ca"]
+        }]"#).unwrap();
+        let tokens: Vec<Token> = parsed.into_iter().map(|tk| Token::new(tk).unwrap()).collect();
+        assert_eq!(to_csv(&tokens).lines().nth(1), Some(r#"F,Foo,,false,"us,ca","a ""quoted"", comma note""#));
+    }
 
     fn read_files() -> Vec<String> {
         let mut lcs = Vec::new();
         for entry in fs::read_dir("./tokens").unwrap() {
             let file_name = entry.unwrap().file_name().into_string().unwrap();
             let file_components: Vec<&str> = file_name.split('.').collect();
-            if file_components[1] == "json" {
+            if file_components[1] == "json" && file_components[0] != GLOBAL {
                 lcs.push(file_components[0].to_owned());
             }
         }
@@ -0,0 +1,617 @@
+use crate::normalize::{base_letter, is_combining_mark};
+use crate::tokenizer::{MatchOptions, Rejection, TokenMatch};
+use crate::{Direction, Replacer, Token};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// What a `Matcher` gets to look at: the tokenizer's tokens, the language each was loaded
+/// from (if known) and its options. `TokenMatch::token_index` indexes into `tokens`.
+pub struct MatchContext<'t> {
+    pub tokens: &'t [&'t Token],
+    pub languages: &'t [Option<&'t str>],
+    pub options: &'t MatchOptions,
+}
+
+/// A strategy for locating tokens in text.
+///
+/// Matchers only propose candidates, which may overlap. The `Tokenizer` checks word
+/// boundaries and length constraints on each candidate and then resolves overlaps by its
+/// precedence rules, so a matcher doesn't have to.
+///
+/// `find` takes a `MatchContext` rather than a slice of tokens: a tokenizer borrows its
+/// tokens, possibly from several languages, and matching needs each one's language and the
+/// tokenizer's options as well.
+pub trait Matcher {
+    fn find(&self, text: &str, context: &MatchContext) -> Vec<TokenMatch>;
+
+    /// Called by `Tokenizer::with_matcher` with the tokenizer's tokens, for matchers that
+    /// precompute something from them. `find` may still be called with other tokens.
+    fn prepare(&mut self, _tokens: &[&Token]) {}
+
+    /// Like `find`, but called by `Tokenizer` with exactly the tokens it gave `prepare`. The
+    /// tokenizer borrows them for as long as it holds the matcher, so what was precomputed
+    /// from them can be trusted without checking. Only the crate can make a `Prepared`, so
+    /// any other caller goes through `find`.
+    fn find_prepared(&self, text: &str, context: &MatchContext, _prepared: Prepared) -> Vec<TokenMatch> {
+        self.find(text, context)
+    }
+}
+
+/// Proof that a `Matcher::find_prepared` call comes from `Tokenizer`.
+pub struct Prepared(pub(crate) ());
+
+/// The default matcher: plain-string tokens match any of their surface forms verbatim (up to
+/// interior whitespace and language-specific equivalences), regex tokens match their pattern.
+/// Diacritics are ignored when comparing, so "Dona" matches "Doña", unless the token sets
+/// `skip_diacritic_stripping`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExactMatcher;
+
+impl Matcher for ExactMatcher {
+    fn find(&self, text: &str, context: &MatchContext) -> Vec<TokenMatch> {
+        let mut candidates = Vec::new();
+        let words = Words::new(text);
+        for (token_index, tk) in context.tokens.iter().enumerate() {
+            match &tk.full {
+                Replacer::String(_) => {
+                    let fold = Fold::for_token(context, token_index);
+                    for form in tk.tokens.iter().filter(|form| !form.is_empty()) {
+                        for (start, end) in find_literal(text, form, &fold, &words) {
+                            candidates.push(literal_match(text, start, end, token_index, context));
+                        }
+                    }
+                },
+                Replacer::Regex { .. } => find_regex(text, token_index, context, &mut candidates)
+            }
+        }
+        candidates
+    }
+}
+
+/// Proposes the same candidates as `ExactMatcher`, but looks plain-string forms up by their
+/// first letter instead of scanning the text once per form, so matching cost grows with the
+/// input rather than with the number of tokens times the input. Regex tokens are still run
+/// one by one.
+///
+/// The index is built once, when the matcher is handed to `Tokenizer::with_matcher`, and used
+/// as it is within the tokenizer. Called directly, the matcher first checks that the forms of
+/// the tokens it is given are those it was prepared for, or else builds a throwaway index
+/// for the call, so it is always correct but only fast within its tokenizer.
+#[derive(Debug, Default)]
+pub struct IndexedMatcher {
+    index: Option<FormIndex>,
+}
+
+#[derive(Debug)]
+struct FormIndex {
+    // A hash of everything the index was built from, see `fingerprint`, to tell whether it
+    // still describes the tokens of a `MatchContext`.
+    fingerprint: u64,
+    // `(token index, form index, word count)` of every non-empty plain-string form, by
+    // `index_key` of the form's first letter. See `word_count` for the last.
+    forms: HashMap<char, Vec<(usize, usize, usize)>>,
+}
+
+impl FormIndex {
+    fn new(tokens: &[&Token]) -> Self {
+        let mut forms: HashMap<char, Vec<(usize, usize, usize)>> = HashMap::new();
+        for (token_index, tk) in tokens.iter().enumerate().filter(|(_, tk)| !tk.regex) {
+            for (form_index, form) in tk.tokens.iter().enumerate() {
+                if let Some(first) = form.chars().next() {
+                    forms.entry(index_key(first)).or_default().push((token_index, form_index, word_count(form)));
+                }
+            }
+        }
+        FormIndex {
+            fingerprint: fingerprint(tokens),
+            forms,
+        }
+    }
+
+    fn describes(&self, tokens: &[&Token]) -> bool {
+        self.fingerprint == fingerprint(tokens)
+    }
+}
+
+// Hashes the forms of `tokens` and which ones are regexes, in order. Comparing addresses
+// would be cheaper, but tokens dropped and reallocated in the same place would then pass for
+// the ones the index was built from.
+fn fingerprint(tokens: &[&Token]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    tokens.len().hash(&mut hasher);
+    for tk in tokens {
+        tk.regex.hash(&mut hasher);
+        tk.tokens.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+// The coarsest equivalence `Fold` can apply to a letter, so that every form whose first
+// letter a text letter might match under some fold shares that letter's key.
+fn index_key(c: char) -> char {
+    let c = Fold { sharp_s: false, diacritics: true, case: true }.letter(c);
+    if c == 'ß' { 's' } else { c }
+}
+
+impl Matcher for IndexedMatcher {
+    fn prepare(&mut self, tokens: &[&Token]) {
+        self.index = Some(FormIndex::new(tokens));
+    }
+
+    fn find(&self, text: &str, context: &MatchContext) -> Vec<TokenMatch> {
+        match &self.index {
+            Some(index) if index.describes(context.tokens) => index.find(text, context),
+            _ => FormIndex::new(context.tokens).find(text, context)
+        }
+    }
+
+    fn find_prepared(&self, text: &str, context: &MatchContext, _prepared: Prepared) -> Vec<TokenMatch> {
+        match &self.index {
+            Some(index) => index.find(text, context),
+            None => self.find(text, context)
+        }
+    }
+}
+
+impl FormIndex {
+    fn find(&self, text: &str, context: &MatchContext) -> Vec<TokenMatch> {
+        let mut candidates = Vec::new();
+        let words = Words::new(text);
+        for (start, c) in text.char_indices() {
+            let forms = match self.forms.get(&index_key(c)) {
+                Some(forms) => forms,
+                None => continue
+            };
+            for &(token_index, form_index, form_words) in forms {
+                if !words.fit(start, form_words) {
+                    continue;
+                }
+                let form = &context.tokens[token_index].tokens[form_index];
+                let fold = Fold::for_token(context, token_index);
+                if !fold.starts(c, form) {
+                    continue;
+                }
+                if let Some(end) = match_at(text, start, form, &fold) {
+                    candidates.push(literal_match(text, start, end, token_index, context));
+                }
+            }
+        }
+        for (token_index, tk) in context.tokens.iter().enumerate().filter(|(_, tk)| tk.regex) {
+            if let Replacer::Regex { .. } = tk.full {
+                find_regex(text, token_index, context, &mut candidates);
+            }
+        }
+        candidates
+    }
+}
+
+fn literal_match(text: &str, start: usize, end: usize, token_index: usize, context: &MatchContext) -> TokenMatch {
+    let tk = context.tokens[token_index];
+    let replacement = tk.preferred_form(context.options.direction);
+    let replacement = match &tk.ascii_canonical {
+        Some(ascii) if replacement == tk.canonical && text[start..end].is_ascii() => ascii,
+        _ => replacement
+    };
+    TokenMatch::new(start, end, token_index, replacement.to_string())
+}
+
+/// Spans a plain-string token would match if it ignored case or diacritics, but doesn't
+/// because it is case-sensitive or sets `skip_diacritic_stripping`, each with the setting
+/// that refused it. `Tokenizer::tokenize_diagnostic` reports these alongside the candidates.
+pub(crate) fn near_misses(text: &str, context: &MatchContext) -> Vec<(TokenMatch, Rejection)> {
+    let mut misses = Vec::new();
+    let words = Words::new(text);
+    for (token_index, tk) in context.tokens.iter().enumerate().filter(|(_, tk)| !tk.regex) {
+        let fold = Fold::for_token(context, token_index);
+        let forms: Vec<&String> = tk.tokens.iter().filter(|form| !form.is_empty()).collect();
+        let mut seen: Vec<(usize, usize)> = forms.iter().flat_map(|form| find_literal(text, form, &fold, &words)).collect();
+        let looser = [
+            (Fold { case: true, ..fold }, Rejection::CaseSensitive),
+            (Fold { diacritics: true, sharp_s: context.languages[token_index] == Some("de"), ..fold }, Rejection::Diacritics),
+        ];
+        for (loose, rejection) in &looser {
+            for form in &forms {
+                for span in find_literal(text, form, loose, &words) {
+                    if !seen.contains(&span) {
+                        seen.push(span);
+                        misses.push((literal_match(text, span.0, span.1, token_index, context), *rejection));
+                    }
+                }
+            }
+        }
+    }
+    misses
+}
+
+fn find_regex(text: &str, token_index: usize, context: &MatchContext, candidates: &mut Vec<TokenMatch>) {
+    let tk = context.tokens[token_index];
+    let (re, names) = match &tk.full {
+        Replacer::Regex { .. } if context.options.direction == Direction::Expand => return,
+        Replacer::Regex { regex, names, .. } => (regex, names),
+        Replacer::String(_) => return
+    };
+    let mut pos = 0;
+    while pos <= text.len() {
+        let caps = match re.captures_from_pos(text, pos) {
+            Ok(Some(caps)) => caps,
+            _ => break
+        };
+        let (start, end) = match caps.pos(0) {
+            Some(span) => span,
+            None => break
+        };
+        candidates.push(TokenMatch::new(start, end, token_index, expand(&tk.canonical, &caps, names)));
+        pos = if end > start {
+            end
+        } else {
+            match text[end..].chars().next() {
+                Some(c) => end + c.len_utf8(),
+                None => break
+            }
+        };
+    }
+}
+
+/// Extends `ExactMatcher` with typo tolerance: a word with no exact match may match a
+/// single-word plain-string form within `MatchOptions::max_edit_distance` (Levenshtein)
+/// edits. Only the closest forms are proposed, and only forms longer than four characters
+/// per allowed edit are considered, so short tokens like "St" never fuzzy-match.
+#[cfg(feature = "fuzzy")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FuzzyMatcher;
+
+#[cfg(feature = "fuzzy")]
+impl Matcher for FuzzyMatcher {
+    fn find(&self, text: &str, context: &MatchContext) -> Vec<TokenMatch> {
+        let mut candidates = ExactMatcher.find(text, context);
+        let max = match context.options.max_edit_distance {
+            Some(max) if max > 0 => max as usize,
+            _ => return candidates
+        };
+
+        let mut fuzzy = Vec::new();
+        for (start, word) in words(text) {
+            let end = start + word.len();
+            if candidates.iter().any(|m| m.start == start && m.end == end) {
+                continue;
+            }
+            let mut best = max + 1;
+            let mut found = Vec::new();
+            for (token_index, tk) in context.tokens.iter().enumerate() {
+                if tk.regex {
+                    continue;
+                }
+                for form in &tk.tokens {
+                    let len = form.chars().count();
+                    if len <= 4 * max || form.contains(char::is_whitespace) {
+                        continue;
+                    }
+                    let distance = levenshtein(word, form);
+                    if distance > max {
+                        continue;
+                    }
+                    if distance < best {
+                        best = distance;
+                        found.clear();
+                    }
+                    if distance == best {
+                        let replacement = tk.preferred_form(context.options.direction).to_string();
+                        found.push(TokenMatch::new(start, end, token_index, replacement));
+                    }
+                }
+            }
+            fuzzy.append(&mut found);
+        }
+        candidates.append(&mut fuzzy);
+        candidates
+    }
+}
+
+#[cfg(feature = "fuzzy")]
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+#[cfg(feature = "fuzzy")]
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+// Character equivalences applied when comparing a plain-string token against input.
+struct Fold {
+    // German "ß" matches "ss" and vice versa.
+    sharp_s: bool,
+    // Letters match regardless of diacritics, see `strip_diacritics`.
+    diacritics: bool,
+    // Letters match regardless of case, where lowercasing is one-to-one.
+    case: bool,
+}
+
+impl Fold {
+    fn for_token(context: &MatchContext, token_index: usize) -> Self {
+        let tk = context.tokens[token_index];
+        Fold {
+            sharp_s: context.languages[token_index] == Some("de") && !tk.skip_diacritic_stripping,
+            diacritics: !tk.skip_diacritic_stripping,
+            case: tk.case_insensitive,
+        }
+    }
+
+    // Whether a match of `form` may start at the text letter `c`.
+    fn starts(&self, c: char, form: &str) -> bool {
+        form.chars().next().is_some_and(|first| self.letter(c) == self.letter(first))
+            || (self.sharp_s && matches!(c, 's' | 'ß'))
+    }
+
+    fn letter(&self, c: char) -> char {
+        let c = if self.diacritics { base_letter(c) } else { c };
+        if !self.case {
+            return c;
+        }
+        let mut lower = c.to_lowercase();
+        match (lower.next(), lower.next()) {
+            (Some(lower), None) => lower,
+            _ => c
+        }
+    }
+}
+
+// Where the whitespace-separated words of a text start, so a matcher can look ahead to
+// whether enough words are left for a multi-word form before comparing it letter by letter.
+struct Words(Vec<usize>);
+
+impl Words {
+    fn new(text: &str) -> Self {
+        let mut starts = Vec::new();
+        let mut in_word = false;
+        for (i, c) in text.char_indices() {
+            if c.is_whitespace() {
+                in_word = false;
+            } else if !in_word {
+                starts.push(i);
+                in_word = true;
+            }
+        }
+        Words(starts)
+    }
+
+    // Whether a form of `words` words (see `word_count`) may match from `start`: each word
+    // after its first needs a word of the text starting after `start`.
+    fn fit(&self, start: usize, words: usize) -> bool {
+        words <= 1 || self.0.len() - self.0.partition_point(|&s| s <= start) >= words - 1
+    }
+}
+
+// The number of words of `form` that each have to match a word of the text, as
+// `Token::word_count` counts them for `full`, leaving out words of only combining marks,
+// which diacritic folding may skip.
+fn word_count(form: &str) -> usize {
+    form.split_whitespace().filter(|word| !word.chars().all(is_combining_mark)).count()
+}
+
+// Finds every occurrence of `form` in `text`, letting each run of whitespace in `form` match
+// any non-empty run of whitespace in `text`, so "Post Office" also matches "Post  Office".
+fn find_literal(text: &str, form: &str, fold: &Fold, words: &Words) -> Vec<(usize, usize)> {
+    let form_words = word_count(form);
+    text.char_indices()
+        .filter(|(start, c)| words.fit(*start, form_words) && fold.starts(*c, form))
+        .filter_map(|(start, _)| match_at(text, start, form, fold).map(|end| (start, end)))
+        .collect()
+}
+
+fn match_at(text: &str, start: usize, form: &str, fold: &Fold) -> Option<usize> {
+    let mut input = text[start..].char_indices().peekable();
+    let mut form = form.chars().peekable();
+    while let Some(f) = form.next() {
+        if fold.diacritics && is_combining_mark(f) {
+            continue;
+        }
+        if f.is_whitespace() {
+            while form.next_if(|c| c.is_whitespace()).is_some() {}
+            input.next_if(|(_, c)| c.is_whitespace())?;
+            while input.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        } else if fold.sharp_s && f == 'ß' {
+            if input.next_if(|(_, c)| *c == 'ß').is_none() {
+                input.next_if(|(_, c)| *c == 's')?;
+                input.next_if(|(_, c)| *c == 's')?;
+            }
+        } else if fold.sharp_s && f == 's' && form.peek() == Some(&'s')
+            && input.next_if(|(_, c)| *c == 'ß').is_some() {
+            form.next();
+        } else if fold.letter(input.next()?.1) != fold.letter(f) {
+            return None;
+        }
+        if fold.diacritics {
+            while input.next_if(|(_, c)| is_combining_mark(*c)).is_some() {}
+        }
+    }
+    Some(input.peek().map_or(text.len(), |(i, _)| start + i))
+}
+
+// Expands `$n`, `${n}`, `$name` and `${name}` group references in a regex token's canonical
+// form.
+fn expand(template: &str, caps: &fancy_regex::Captures, names: &HashMap<String, usize>) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let (reference, skip) = if rest.starts_with('{') {
+            match rest.find('}') {
+                Some(close) => (&rest[1..close], close + 1),
+                None => ("", 0)
+            }
+        } else if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            let len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            (&rest[..len], len)
+        } else {
+            let len = rest.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(rest.len());
+            (&rest[..len], len)
+        };
+        match reference.parse::<usize>().ok().or_else(|| names.get(reference).copied()) {
+            Some(group) => {
+                out.push_str(caps.at(group).unwrap_or(""));
+                rest = &rest[skip..];
+            },
+            None => out.push('$')
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, config_one, config_with, ConfigOptions, Tokenizer};
+
+    #[test]
+    fn test_indexed_matcher() {
+        let inputs = [
+            "Main Street", "123 North Main Street Apartment 4", "Fort Worth Avenue", "Streetsboro",
+            "Berliner Straße", "Berliner Strasse", "Hauptstr. 5", "Avenida Doña Perpetua", "1st Avenue Northwest",
+            "Rue du Faubourg Saint-Honoré", "Ulica Marszałkowska", "MAIN STREET", "main street", "",
+        ];
+        let options = ConfigOptions::new().case_insensitive(true);
+        for map in [config(Vec::new()).unwrap(), config_with(options).unwrap()] {
+            for (lc, tokens) in &map {
+                let exact = Tokenizer::for_language(lc, tokens);
+                let indexed = Tokenizer::for_language(lc, tokens).with_matcher(IndexedMatcher::default());
+                for input in &inputs {
+                    assert_eq!(indexed.tokenize(input), exact.tokenize(input), "{}: {:?}", lc, input);
+                    assert_eq!(indexed.find_tokens(input), exact.find_tokens(input), "{}: {:?}", lc, input);
+                }
+            }
+        }
+
+        // Reused with other tokens, the matcher still finds those tokens' forms.
+        let map = config(vec![String::from("en"), String::from("de")]).unwrap();
+        let mut matcher = IndexedMatcher::default();
+        for (lc, text) in [("en", "Main Street"), ("de", "Berliner Straße")] {
+            let tokens: Vec<&Token> = map[lc].iter().collect();
+            assert_same_candidates(&matcher, &tokens, text);
+        }
+        matcher.prepare(&map["en"].iter().collect::<Vec<&Token>>());
+        let tokens: Vec<&Token> = map["de"].iter().collect();
+        assert_same_candidates(&matcher, &tokens, "Berliner Straße");
+    }
+
+    #[test]
+    fn test_indexed_matcher_stale_tokens() {
+        let en = config_one("en").unwrap();
+        let mut tokens: Vec<&Token> = en.iter().collect();
+        let mut matcher = IndexedMatcher::default();
+        matcher.prepare(&tokens);
+        let text = "Main Street Northwest 1st Avenue";
+        assert_same_candidates(&matcher, &tokens, text);
+
+        // The same Vec, truncated: the index must not point past its end.
+        tokens.truncate(3);
+        assert_same_candidates(&matcher, &tokens, text);
+
+        // The same allocation, refilled with other tokens at the same length.
+        tokens.clear();
+        tokens.extend(en.iter().rev());
+        assert_same_candidates(&matcher, &tokens, text);
+
+        // The same tokens at the same addresses, with their forms changed.
+        let mut owned = config_one("en").unwrap();
+        let mut matcher = IndexedMatcher::default();
+        matcher.prepare(&owned.iter().collect::<Vec<&Token>>());
+        for tk in owned.iter_mut() {
+            tk.tokens.truncate(1);
+        }
+        assert_same_candidates(&matcher, &owned.iter().collect::<Vec<&Token>>(), text);
+
+        // The same addresses and form counts, with other forms: only their text tells them apart.
+        let mut owned = config_one("en").unwrap();
+        let mut matcher = IndexedMatcher::default();
+        matcher.prepare(&owned.iter().collect::<Vec<&Token>>());
+        for tk in owned.iter_mut().filter(|tk| !tk.regex) {
+            for form in tk.tokens.iter_mut() {
+                *form = format!("{}x", form);
+            }
+        }
+        owned[0].tokens[0] = String::from("Main");
+        assert_same_candidates(&matcher, &owned.iter().collect::<Vec<&Token>>(), text);
+        assert!(!ExactMatcher.find(text, &MatchContext {
+            tokens: &owned.iter().collect::<Vec<&Token>>(),
+            languages: &vec![None; owned.len()],
+            options: &MatchOptions::default(),
+        }).is_empty());
+    }
+
+    #[test]
+    fn test_word_look_ahead() {
+        let words = Words::new(" Post  Office Box");
+        assert!(words.fit(1, 3));
+        assert!(words.fit(7, 2));
+        assert!(!words.fit(7, 3));
+        assert!(words.fit(14, 1));
+        assert!(!words.fit(14, 2));
+        assert_eq!(word_count("Post Office Box"), 3);
+        assert_eq!(word_count("Street"), 1);
+
+        let fold = Fold { sharp_s: false, diacritics: false, case: false };
+        assert_eq!(find_literal("Post  Office", "Post Office", &fold, &Words::new("Post  Office")), vec![(0, 12)]);
+        assert!(find_literal("Post Office", "Post Office Box", &fold, &Words::new("Post Office")).is_empty());
+    }
+
+    fn assert_same_candidates(matcher: &IndexedMatcher, tokens: &[&Token], text: &str) {
+        let options = MatchOptions::default();
+        let languages = vec![None; tokens.len()];
+        let context = MatchContext { tokens, languages: &languages, options: &options };
+        let mut indexed = matcher.find(text, &context);
+        let mut exact = ExactMatcher.find(text, &context);
+        indexed.sort_by_key(|m| (m.start, m.end, m.token_index));
+        exact.sort_by_key(|m| (m.start, m.end, m.token_index));
+        assert_eq!(indexed, exact);
+    }
+
+    #[cfg(feature = "fuzzy")]
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("Avanue", "Avenue"), 1);
+        assert_eq!(levenshtein("Steet", "Street"), 1);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+
+    #[cfg(feature = "fuzzy")]
+    #[test]
+    fn test_fuzzy_matcher() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let options = MatchOptions {
+            max_edit_distance: Some(1),
+            ..MatchOptions::default()
+        };
+        let tokenizer = Tokenizer::with_options(&map["en"], options).with_matcher(FuzzyMatcher);
+
+        assert_eq!(tokenizer.tokenize("Main Avanue"), "Main Av");
+        assert_eq!(tokenizer.tokenize("Main Steet"), "Main St");
+        assert_eq!(tokenizer.tokenize("Main Street"), "Main St");
+        assert_eq!(tokenizer.tokenize("Main Avnu"), "Main Avnu");
+        assert_eq!(tokenizer.tokenize("Main Sx"), "Main Sx");
+
+        let strict = Tokenizer::new(&map["en"]).with_matcher(FuzzyMatcher);
+        assert_eq!(strict.tokenize("Main Avanue"), "Main Avanue");
+    }
+}
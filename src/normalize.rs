@@ -0,0 +1,172 @@
+use std::borrow::Cow;
+
+/// Clean-up applied to input text before tokens are matched against it.
+#[derive(Debug, Default, Clone)]
+pub struct NormalizeOptions {
+    /// Drops zero-width and bidi control characters and turns the other Unicode space
+    /// separators (no-break space, thin space, ideographic space, ...) into plain spaces.
+    /// Note that this also drops joiners some scripts rely on, e.g. in Sinhala.
+    pub strip_control: bool,
+    /// Turns each line break ("\n", "\r\n" or "\r") into a single space, so multi-line
+    /// address blocks come out on one line.
+    pub newline_as_space: bool,
+}
+
+/// Applies `options` to `text`, borrowing it when there is nothing to change.
+pub fn normalize<'t>(text: &'t str, options: &NormalizeOptions) -> Cow<'t, str> {
+    let mut text = Cow::Borrowed(text);
+    if options.newline_as_space && text.contains(['\n', '\r']) {
+        text = Cow::Owned(text.replace("\r\n", " ").replace(['\n', '\r'], " "));
+    }
+    if options.strip_control && text.chars().any(|c| is_control(c) || is_space(c)) {
+        text = Cow::Owned(text.chars()
+            .filter(|c| !is_control(*c))
+            .map(|c| if is_space(c) { ' ' } else { c })
+            .collect());
+    }
+    text
+}
+
+/// Removes diacritical marks from `s`, e.g. "Doña" becomes "Dona": precomposed Latin
+/// letters are replaced with their base letter and combining marks are dropped. Letters
+/// outside the Latin script, such as Cyrillic "й", are left as they are.
+pub fn strip_diacritics(s: &str) -> String {
+    s.chars().filter(|c| !is_combining_mark(*c)).map(base_letter).collect()
+}
+
+/// The letter `c` is a Latin letter with diacritics on top of, or `c` itself.
+pub(crate) fn base_letter(c: char) -> char {
+    if (c as u32) < 0xC0 {
+        return c;
+    }
+    match DECOMPOSED.binary_search_by_key(&c, |&(composed, _)| composed) {
+        Ok(i) => DECOMPOSED[i].1,
+        Err(_) => c
+    }
+}
+
+pub(crate) fn is_combining_mark(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+// Precomposed Latin letters whose canonical decomposition is an ASCII letter followed by
+// combining marks, by code point.
+const DECOMPOSED: &[(char, char)] = &[
+    ('À', 'A'), ('Á', 'A'), ('Â', 'A'), ('Ã', 'A'), ('Ä', 'A'), ('Å', 'A'), ('Ç', 'C'), ('È', 'E'),
+    ('É', 'E'), ('Ê', 'E'), ('Ë', 'E'), ('Ì', 'I'), ('Í', 'I'), ('Î', 'I'), ('Ï', 'I'), ('Ñ', 'N'),
+    ('Ò', 'O'), ('Ó', 'O'), ('Ô', 'O'), ('Õ', 'O'), ('Ö', 'O'), ('Ù', 'U'), ('Ú', 'U'), ('Û', 'U'),
+    ('Ü', 'U'), ('Ý', 'Y'), ('à', 'a'), ('á', 'a'), ('â', 'a'), ('ã', 'a'), ('ä', 'a'), ('å', 'a'),
+    ('ç', 'c'), ('è', 'e'), ('é', 'e'), ('ê', 'e'), ('ë', 'e'), ('ì', 'i'), ('í', 'i'), ('î', 'i'),
+    ('ï', 'i'), ('ñ', 'n'), ('ò', 'o'), ('ó', 'o'), ('ô', 'o'), ('õ', 'o'), ('ö', 'o'), ('ù', 'u'),
+    ('ú', 'u'), ('û', 'u'), ('ü', 'u'), ('ý', 'y'), ('ÿ', 'y'), ('Ā', 'A'), ('ā', 'a'), ('Ă', 'A'),
+    ('ă', 'a'), ('Ą', 'A'), ('ą', 'a'), ('Ć', 'C'), ('ć', 'c'), ('Ĉ', 'C'), ('ĉ', 'c'), ('Ċ', 'C'),
+    ('ċ', 'c'), ('Č', 'C'), ('č', 'c'), ('Ď', 'D'), ('ď', 'd'), ('Ē', 'E'), ('ē', 'e'), ('Ĕ', 'E'),
+    ('ĕ', 'e'), ('Ė', 'E'), ('ė', 'e'), ('Ę', 'E'), ('ę', 'e'), ('Ě', 'E'), ('ě', 'e'), ('Ĝ', 'G'),
+    ('ĝ', 'g'), ('Ğ', 'G'), ('ğ', 'g'), ('Ġ', 'G'), ('ġ', 'g'), ('Ģ', 'G'), ('ģ', 'g'), ('Ĥ', 'H'),
+    ('ĥ', 'h'), ('Ĩ', 'I'), ('ĩ', 'i'), ('Ī', 'I'), ('ī', 'i'), ('Ĭ', 'I'), ('ĭ', 'i'), ('Į', 'I'),
+    ('į', 'i'), ('İ', 'I'), ('Ĵ', 'J'), ('ĵ', 'j'), ('Ķ', 'K'), ('ķ', 'k'), ('Ĺ', 'L'), ('ĺ', 'l'),
+    ('Ļ', 'L'), ('ļ', 'l'), ('Ľ', 'L'), ('ľ', 'l'), ('Ń', 'N'), ('ń', 'n'), ('Ņ', 'N'), ('ņ', 'n'),
+    ('Ň', 'N'), ('ň', 'n'), ('Ō', 'O'), ('ō', 'o'), ('Ŏ', 'O'), ('ŏ', 'o'), ('Ő', 'O'), ('ő', 'o'),
+    ('Ŕ', 'R'), ('ŕ', 'r'), ('Ŗ', 'R'), ('ŗ', 'r'), ('Ř', 'R'), ('ř', 'r'), ('Ś', 'S'), ('ś', 's'),
+    ('Ŝ', 'S'), ('ŝ', 's'), ('Ş', 'S'), ('ş', 's'), ('Š', 'S'), ('š', 's'), ('Ţ', 'T'), ('ţ', 't'),
+    ('Ť', 'T'), ('ť', 't'), ('Ũ', 'U'), ('ũ', 'u'), ('Ū', 'U'), ('ū', 'u'), ('Ŭ', 'U'), ('ŭ', 'u'),
+    ('Ů', 'U'), ('ů', 'u'), ('Ű', 'U'), ('ű', 'u'), ('Ų', 'U'), ('ų', 'u'), ('Ŵ', 'W'), ('ŵ', 'w'),
+    ('Ŷ', 'Y'), ('ŷ', 'y'), ('Ÿ', 'Y'), ('Ź', 'Z'), ('ź', 'z'), ('Ż', 'Z'), ('ż', 'z'), ('Ž', 'Z'),
+    ('ž', 'z'), ('Ơ', 'O'), ('ơ', 'o'), ('Ư', 'U'), ('ư', 'u'), ('Ǎ', 'A'), ('ǎ', 'a'), ('Ǐ', 'I'),
+    ('ǐ', 'i'), ('Ǒ', 'O'), ('ǒ', 'o'), ('Ǔ', 'U'), ('ǔ', 'u'), ('Ǖ', 'U'), ('ǖ', 'u'), ('Ǘ', 'U'),
+    ('ǘ', 'u'), ('Ǚ', 'U'), ('ǚ', 'u'), ('Ǜ', 'U'), ('ǜ', 'u'), ('Ǟ', 'A'), ('ǟ', 'a'), ('Ǡ', 'A'),
+    ('ǡ', 'a'), ('Ǧ', 'G'), ('ǧ', 'g'), ('Ǩ', 'K'), ('ǩ', 'k'), ('Ǫ', 'O'), ('ǫ', 'o'), ('Ǭ', 'O'),
+    ('ǭ', 'o'), ('ǰ', 'j'), ('Ǵ', 'G'), ('ǵ', 'g'), ('Ǹ', 'N'), ('ǹ', 'n'), ('Ǻ', 'A'), ('ǻ', 'a'),
+    ('Ȁ', 'A'), ('ȁ', 'a'), ('Ȃ', 'A'), ('ȃ', 'a'), ('Ȅ', 'E'), ('ȅ', 'e'), ('Ȇ', 'E'), ('ȇ', 'e'),
+    ('Ȉ', 'I'), ('ȉ', 'i'), ('Ȋ', 'I'), ('ȋ', 'i'), ('Ȍ', 'O'), ('ȍ', 'o'), ('Ȏ', 'O'), ('ȏ', 'o'),
+    ('Ȑ', 'R'), ('ȑ', 'r'), ('Ȓ', 'R'), ('ȓ', 'r'), ('Ȕ', 'U'), ('ȕ', 'u'), ('Ȗ', 'U'), ('ȗ', 'u'),
+    ('Ș', 'S'), ('ș', 's'), ('Ț', 'T'), ('ț', 't'), ('Ȟ', 'H'), ('ȟ', 'h'), ('Ȧ', 'A'), ('ȧ', 'a'),
+    ('Ȩ', 'E'), ('ȩ', 'e'), ('Ȫ', 'O'), ('ȫ', 'o'), ('Ȭ', 'O'), ('ȭ', 'o'), ('Ȯ', 'O'), ('ȯ', 'o'),
+    ('Ȱ', 'O'), ('ȱ', 'o'), ('Ȳ', 'Y'), ('ȳ', 'y'), ('Ḁ', 'A'), ('ḁ', 'a'), ('Ḃ', 'B'), ('ḃ', 'b'),
+    ('Ḅ', 'B'), ('ḅ', 'b'), ('Ḇ', 'B'), ('ḇ', 'b'), ('Ḉ', 'C'), ('ḉ', 'c'), ('Ḋ', 'D'), ('ḋ', 'd'),
+    ('Ḍ', 'D'), ('ḍ', 'd'), ('Ḏ', 'D'), ('ḏ', 'd'), ('Ḑ', 'D'), ('ḑ', 'd'), ('Ḓ', 'D'), ('ḓ', 'd'),
+    ('Ḕ', 'E'), ('ḕ', 'e'), ('Ḗ', 'E'), ('ḗ', 'e'), ('Ḙ', 'E'), ('ḙ', 'e'), ('Ḛ', 'E'), ('ḛ', 'e'),
+    ('Ḝ', 'E'), ('ḝ', 'e'), ('Ḟ', 'F'), ('ḟ', 'f'), ('Ḡ', 'G'), ('ḡ', 'g'), ('Ḣ', 'H'), ('ḣ', 'h'),
+    ('Ḥ', 'H'), ('ḥ', 'h'), ('Ḧ', 'H'), ('ḧ', 'h'), ('Ḩ', 'H'), ('ḩ', 'h'), ('Ḫ', 'H'), ('ḫ', 'h'),
+    ('Ḭ', 'I'), ('ḭ', 'i'), ('Ḯ', 'I'), ('ḯ', 'i'), ('Ḱ', 'K'), ('ḱ', 'k'), ('Ḳ', 'K'), ('ḳ', 'k'),
+    ('Ḵ', 'K'), ('ḵ', 'k'), ('Ḷ', 'L'), ('ḷ', 'l'), ('Ḹ', 'L'), ('ḹ', 'l'), ('Ḻ', 'L'), ('ḻ', 'l'),
+    ('Ḽ', 'L'), ('ḽ', 'l'), ('Ḿ', 'M'), ('ḿ', 'm'), ('Ṁ', 'M'), ('ṁ', 'm'), ('Ṃ', 'M'), ('ṃ', 'm'),
+    ('Ṅ', 'N'), ('ṅ', 'n'), ('Ṇ', 'N'), ('ṇ', 'n'), ('Ṉ', 'N'), ('ṉ', 'n'), ('Ṋ', 'N'), ('ṋ', 'n'),
+    ('Ṍ', 'O'), ('ṍ', 'o'), ('Ṏ', 'O'), ('ṏ', 'o'), ('Ṑ', 'O'), ('ṑ', 'o'), ('Ṓ', 'O'), ('ṓ', 'o'),
+    ('Ṕ', 'P'), ('ṕ', 'p'), ('Ṗ', 'P'), ('ṗ', 'p'), ('Ṙ', 'R'), ('ṙ', 'r'), ('Ṛ', 'R'), ('ṛ', 'r'),
+    ('Ṝ', 'R'), ('ṝ', 'r'), ('Ṟ', 'R'), ('ṟ', 'r'), ('Ṡ', 'S'), ('ṡ', 's'), ('Ṣ', 'S'), ('ṣ', 's'),
+    ('Ṥ', 'S'), ('ṥ', 's'), ('Ṧ', 'S'), ('ṧ', 's'), ('Ṩ', 'S'), ('ṩ', 's'), ('Ṫ', 'T'), ('ṫ', 't'),
+    ('Ṭ', 'T'), ('ṭ', 't'), ('Ṯ', 'T'), ('ṯ', 't'), ('Ṱ', 'T'), ('ṱ', 't'), ('Ṳ', 'U'), ('ṳ', 'u'),
+    ('Ṵ', 'U'), ('ṵ', 'u'), ('Ṷ', 'U'), ('ṷ', 'u'), ('Ṹ', 'U'), ('ṹ', 'u'), ('Ṻ', 'U'), ('ṻ', 'u'),
+    ('Ṽ', 'V'), ('ṽ', 'v'), ('Ṿ', 'V'), ('ṿ', 'v'), ('Ẁ', 'W'), ('ẁ', 'w'), ('Ẃ', 'W'), ('ẃ', 'w'),
+    ('Ẅ', 'W'), ('ẅ', 'w'), ('Ẇ', 'W'), ('ẇ', 'w'), ('Ẉ', 'W'), ('ẉ', 'w'), ('Ẋ', 'X'), ('ẋ', 'x'),
+    ('Ẍ', 'X'), ('ẍ', 'x'), ('Ẏ', 'Y'), ('ẏ', 'y'), ('Ẑ', 'Z'), ('ẑ', 'z'), ('Ẓ', 'Z'), ('ẓ', 'z'),
+    ('Ẕ', 'Z'), ('ẕ', 'z'), ('ẖ', 'h'), ('ẗ', 't'), ('ẘ', 'w'), ('ẙ', 'y'), ('Ạ', 'A'), ('ạ', 'a'),
+    ('Ả', 'A'), ('ả', 'a'), ('Ấ', 'A'), ('ấ', 'a'), ('Ầ', 'A'), ('ầ', 'a'), ('Ẩ', 'A'), ('ẩ', 'a'),
+    ('Ẫ', 'A'), ('ẫ', 'a'), ('Ậ', 'A'), ('ậ', 'a'), ('Ắ', 'A'), ('ắ', 'a'), ('Ằ', 'A'), ('ằ', 'a'),
+    ('Ẳ', 'A'), ('ẳ', 'a'), ('Ẵ', 'A'), ('ẵ', 'a'), ('Ặ', 'A'), ('ặ', 'a'), ('Ẹ', 'E'), ('ẹ', 'e'),
+    ('Ẻ', 'E'), ('ẻ', 'e'), ('Ẽ', 'E'), ('ẽ', 'e'), ('Ế', 'E'), ('ế', 'e'), ('Ề', 'E'), ('ề', 'e'),
+    ('Ể', 'E'), ('ể', 'e'), ('Ễ', 'E'), ('ễ', 'e'), ('Ệ', 'E'), ('ệ', 'e'), ('Ỉ', 'I'), ('ỉ', 'i'),
+    ('Ị', 'I'), ('ị', 'i'), ('Ọ', 'O'), ('ọ', 'o'), ('Ỏ', 'O'), ('ỏ', 'o'), ('Ố', 'O'), ('ố', 'o'),
+    ('Ồ', 'O'), ('ồ', 'o'), ('Ổ', 'O'), ('ổ', 'o'), ('Ỗ', 'O'), ('ỗ', 'o'), ('Ộ', 'O'), ('ộ', 'o'),
+    ('Ớ', 'O'), ('ớ', 'o'), ('Ờ', 'O'), ('ờ', 'o'), ('Ở', 'O'), ('ở', 'o'), ('Ỡ', 'O'), ('ỡ', 'o'),
+    ('Ợ', 'O'), ('ợ', 'o'), ('Ụ', 'U'), ('ụ', 'u'), ('Ủ', 'U'), ('ủ', 'u'), ('Ứ', 'U'), ('ứ', 'u'),
+    ('Ừ', 'U'), ('ừ', 'u'), ('Ử', 'U'), ('ử', 'u'), ('Ữ', 'U'), ('ữ', 'u'), ('Ự', 'U'), ('ự', 'u'),
+    ('Ỳ', 'Y'), ('ỳ', 'y'), ('Ỵ', 'Y'), ('ỵ', 'y'), ('Ỷ', 'Y'), ('ỷ', 'y'), ('Ỹ', 'Y'), ('ỹ', 'y'),
+];
+
+fn is_control(c: char) -> bool {
+    matches!(c,
+        '\u{061C}' // Arabic letter mark
+        | '\u{200B}'..='\u{200F}' // zero-width space, (non-)joiner, LTR/RTL marks
+        | '\u{202A}'..='\u{202E}' // bidi embeddings and overrides
+        | '\u{2060}' // word joiner
+        | '\u{2066}'..='\u{2069}' // bidi isolates
+        | '\u{FEFF}' // zero-width no-break space
+    )
+}
+
+fn is_space(c: char) -> bool {
+    matches!(c, '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let options = NormalizeOptions {
+            strip_control: true,
+            ..NormalizeOptions::default()
+        };
+        assert_eq!(normalize("Main\u{00A0}\u{200B}Street\u{200E}", &options), "Main Street");
+        assert!(matches!(normalize("Main Street", &options), Cow::Borrowed(_)));
+        assert_eq!(normalize("Main\u{00A0}Street", &NormalizeOptions::default()), "Main\u{00A0}Street");
+    }
+
+    #[test]
+    fn test_strip_diacritics() {
+        assert_eq!(strip_diacritics("Doña Ávila"), "Dona Avila");
+        assert_eq!(strip_diacritics("Circunvalaci\u{006F}\u{0301}n"), "Circunvalacion");
+        assert_eq!(strip_diacritics("Đường Nguyễn Huệ"), "Đuong Nguyen Hue");
+        assert_eq!(strip_diacritics("улица Чайковского"), "улица Чайковского");
+        assert!(DECOMPOSED.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn test_newline_as_space() {
+        let options = NormalizeOptions {
+            newline_as_space: true,
+            ..NormalizeOptions::default()
+        };
+        assert_eq!(normalize("12 Main\r\nStreet\nSpringfield\r", &options), "12 Main Street Springfield ");
+        assert_eq!(normalize("Main\nStreet", &NormalizeOptions::default()), "Main\nStreet");
+    }
+}
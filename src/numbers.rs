@@ -0,0 +1,242 @@
+use crate::{abbreviate, config, Token, TokenType};
+use fancy_regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+struct NumberWords {
+    cardinals: HashMap<String, u64>,
+    ordinals: HashMap<String, u64>,
+}
+
+// Small numbers and ordinals come from the `number`/`ordinal` tokens in `en.json`; the tens
+// and scale words the token file doesn't carry are filled in here.
+fn number_words() -> &'static NumberWords {
+    static WORDS: OnceLock<NumberWords> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        let mut words = NumberWords {
+            cardinals: HashMap::new(),
+            ordinals: HashMap::new(),
+        };
+        if let Ok(map) = config(vec![String::from("en")]) {
+            for tk in &map["en"] {
+                let value = tk.canonical.trim_end_matches(|c: char| !c.is_ascii_digit()).parse::<u64>();
+                let value = match value {
+                    Ok(value) => value,
+                    Err(_) => continue
+                };
+                let full = tk.full.as_str().to_lowercase();
+                match tk.token_type {
+                    Some(TokenType::Number) => { words.cardinals.insert(full, value); },
+                    Some(TokenType::Ordinal) => { words.ordinals.insert(full, value); },
+                    _ => ()
+                }
+            }
+        }
+        for (i, (cardinal, ordinal)) in [
+            ("twenty", "twentieth"),
+            ("thirty", "thirtieth"),
+            ("forty", "fortieth"),
+            ("fifty", "fiftieth"),
+            ("sixty", "sixtieth"),
+            ("seventy", "seventieth"),
+            ("eighty", "eightieth"),
+            ("ninety", "ninetieth"),
+        ].iter().enumerate() {
+            let value = (i as u64 + 2) * 10;
+            words.cardinals.entry(cardinal.to_string()).or_insert(value);
+            words.ordinals.entry(ordinal.to_string()).or_insert(value);
+        }
+        for (word, value) in [
+            ("zero", 0),
+            ("hundred", 100),
+            ("thousand", THOUSAND),
+            ("million", MILLION),
+            ("billion", BILLION),
+        ].iter() {
+            words.cardinals.entry(word.to_string()).or_insert(*value);
+        }
+        for (word, value) in [
+            ("zeroth", 0),
+            ("hundredth", 100),
+            ("thousandth", THOUSAND),
+            ("millionth", MILLION),
+            ("billionth", BILLION),
+        ].iter() {
+            words.ordinals.entry(word.to_string()).or_insert(*value);
+        }
+        words
+    })
+}
+
+const THOUSAND: u64 = 1_000;
+const MILLION: u64 = 1_000_000;
+const BILLION: u64 = 1_000_000_000;
+
+/// Parses an English number written out in words, e.g. "twenty third" or
+/// "one hundred twenty-three", into digits. Ordinals keep their suffix ("23rd").
+/// Returns `None` unless the whole input is a number. Scale words must come in decreasing
+/// order, as in "two million three thousand", so "thousand thousand" isn't a number, and
+/// neither is anything that doesn't fit in a `u64`.
+pub fn words_to_number(input: &str) -> Option<String> {
+    let words = number_words();
+    let parts: Vec<String> = input
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .filter(|part| !part.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if parts.is_empty() {
+        return None;
+    }
+
+    let (mut total, mut current) = (0u64, 0u64);
+    let mut last_scale = None;
+    let mut ordinal = false;
+    for (i, part) in parts.iter().enumerate() {
+        if part == "and" && i > 0 && i + 1 < parts.len() {
+            continue;
+        }
+        let value = match words.cardinals.get(part) {
+            Some(value) => *value,
+            None if i + 1 == parts.len() => {
+                ordinal = true;
+                *words.ordinals.get(part)?
+            },
+            None => return None
+        };
+        match value {
+            100 if current >= 100 => return None,
+            100 => current = current.max(1) * 100,
+            THOUSAND | MILLION | BILLION => {
+                if last_scale.is_some_and(|last| value >= last) {
+                    return None;
+                }
+                total = total.checked_add(current.max(1).checked_mul(value)?)?;
+                current = 0;
+                last_scale = Some(value);
+            },
+            v if v < 10 && current % 10 != 0 => return None,
+            v if v >= 10 && current % 100 != 0 => return None,
+            v => current = current.checked_add(v)?
+        }
+    }
+
+    let number = total.checked_add(current)?;
+    if !ordinal {
+        return Some(number.to_string());
+    }
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th"
+    };
+    Some(format!("{}{}", number, suffix))
+}
+
+/// Rewrites house number ranges to a consistent "123-125" form, whatever spacing or dash
+/// (hyphen, en dash, em dash) the input used. Either end may carry a one-letter suffix, as
+/// in "12A - 12C".
+pub fn normalize_number_ranges(input: &str) -> String {
+    static RANGE: OnceLock<Option<Regex>> = OnceLock::new();
+    let range = RANGE.get_or_init(|| {
+        Regex::new(r"(?<![\w-])(\d+[[:alpha:]]?)[ \t]*[‐–—-][ \t]*(\d+[[:alpha:]]?)(?![\w-])").ok()
+    });
+    let range = match range {
+        Some(range) => range,
+        None => return input.to_string()
+    };
+    let mut out = String::with_capacity(input.len());
+    let mut last = 0;
+    let mut pos = 0;
+    while let Ok(Some(caps)) = range.captures_from_pos(input, pos) {
+        let (start, end) = match caps.pos(0) {
+            Some(span) => span,
+            None => break
+        };
+        out.push_str(&input[last..start]);
+        out.push_str(caps.at(1).unwrap_or(""));
+        out.push('-');
+        out.push_str(caps.at(2).unwrap_or(""));
+        last = end;
+        pos = end;
+    }
+    out.push_str(&input[last..]);
+    out
+}
+
+/// Abbreviates `input` with only the `Number` and `Ordinal` tokens in `tokens`, so "First
+/// Street" becomes "1st Street" and the rest of the text is left alone. This is the subset
+/// of a full `abbreviate` pass those tokens would make; languages without typed number
+/// tokens, such as "de", are returned unchanged.
+pub fn normalize_numerals(input: &str, tokens: &[Token]) -> String {
+    let numerals: Vec<Token> = tokens.iter()
+        .filter(|tk| matches!(tk.token_type, Some(TokenType::Number) | Some(TokenType::Ordinal)))
+        .cloned()
+        .collect();
+    abbreviate(input, &numerals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_to_number() {
+        assert_eq!(words_to_number("first"), Some(String::from("1st")));
+        assert_eq!(words_to_number("First"), Some(String::from("1st")));
+        assert_eq!(words_to_number("twenty third"), Some(String::from("23rd")));
+        assert_eq!(words_to_number("twenty-three"), Some(String::from("23")));
+        assert_eq!(words_to_number("one hundred twenty third"), Some(String::from("123rd")));
+        assert_eq!(words_to_number("two thousand and eleventh"), Some(String::from("2011th")));
+        assert_eq!(words_to_number("ninety"), Some(String::from("90")));
+
+        assert_eq!(words_to_number(""), None);
+        assert_eq!(words_to_number("first street"), None);
+        assert_eq!(words_to_number("twenty thirty"), None);
+    }
+
+    #[test]
+    fn test_words_to_number_scales() {
+        assert_eq!(words_to_number("two million three thousand and five"), Some(String::from("2003005")));
+        assert_eq!(words_to_number("one billion two hundred million"), Some(String::from("1200000000")));
+        assert_eq!(words_to_number("three millionth"), Some(String::from("3000000th")));
+
+        assert_eq!(words_to_number("thousand thousand"), None);
+        assert_eq!(words_to_number("two thousand three thousand"), None);
+        assert_eq!(words_to_number("one thousand million"), None);
+        assert_eq!(words_to_number("one hundred hundred"), None);
+    }
+
+    #[test]
+    fn test_words_to_number_overflow() {
+        assert_eq!(words_to_number(&"thousand ".repeat(40)), None);
+        assert_eq!(words_to_number(&"hundred ".repeat(40)), None);
+        assert_eq!(words_to_number(&"one ".repeat(40)), None);
+        assert_eq!(
+            words_to_number("nine hundred ninety nine billion nine hundred ninety nine thousand"),
+            Some(String::from("999000999000"))
+        );
+    }
+
+    #[test]
+    fn test_normalize_numerals() {
+        let map = config(vec![String::from("en"), String::from("fr"), String::from("de")]).unwrap();
+
+        assert_eq!(normalize_numerals("First Street", &map["en"]), "1st Street");
+        assert_eq!(normalize_numerals("Tenth Avenue Northwest", &map["en"]), "10th Avenue Northwest");
+        assert_eq!(normalize_numerals("one Main Street", &map["en"]), "1 Main Street");
+        assert_eq!(normalize_numerals("Dixième Avenue", &map["fr"]), "10e Avenue");
+        assert_eq!(normalize_numerals("Erste Straße", &map["de"]), "Erste Straße");
+    }
+
+    #[test]
+    fn test_normalize_number_ranges() {
+        assert_eq!(normalize_number_ranges("123 - 125 Main St"), "123-125 Main St");
+        assert_eq!(normalize_number_ranges("123–125 Main St"), "123-125 Main St");
+        assert_eq!(normalize_number_ranges("12A -12C, 4 - 6 Elm St"), "12A-12C, 4-6 Elm St");
+        assert_eq!(normalize_number_ranges("123-125 Main St"), "123-125 Main St");
+        assert_eq!(normalize_number_ranges("555-123-4567"), "555-123-4567");
+        assert_eq!(normalize_number_ranges("Route 9 - Exit 4"), "Route 9 - Exit 4");
+    }
+}
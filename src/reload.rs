@@ -0,0 +1,60 @@
+use crate::{prepare_dir, Error, Token};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{RwLock, RwLockReadGuard};
+
+/// A token map that can be rebuilt from a directory of `<lc>.json` files while it is being
+/// read from other threads.
+pub struct ReloadableConfig {
+    tokens: RwLock<HashMap<String, Vec<Token>>>,
+}
+
+impl ReloadableConfig {
+    pub fn new(tokens: HashMap<String, Vec<Token>>) -> Self {
+        ReloadableConfig {
+            tokens: RwLock::new(tokens),
+        }
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, HashMap<String, Vec<Token>>> {
+        self.tokens.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Parses every token file in `dir` and swaps the result in. The current tokens are left
+    /// untouched if any file fails to load.
+    pub fn reload(&self, dir: &Path) -> Result<(), Error> {
+        let tokens = prepare_dir(dir)?;
+        *self.tokens.write().unwrap_or_else(|e| e.into_inner()) = tokens;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use std::fs;
+
+    #[test]
+    fn test_reload() {
+        let dir = std::env::temp_dir().join(format!("geocoder-abbreviations-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let reloadable = ReloadableConfig::new(config(vec![String::from("en")]).unwrap());
+        assert!(reloadable.read().contains_key("en"));
+
+        fs::write(dir.join("xx.json"), r#"[{ "tokens": ["Foo", "F"], "full": "Foo", "canonical": "F" }]"#).unwrap();
+        reloadable.reload(&dir).unwrap();
+        assert!(!reloadable.read().contains_key("en"));
+        assert_eq!(reloadable.read()["xx"][0].canonical, "F");
+
+        fs::write(dir.join("xx.json"), r#"[{ "tokens": ["Bar", "B"], "full": "Bar", "canonical": "B" }]"#).unwrap();
+        reloadable.reload(&dir).unwrap();
+        assert_eq!(reloadable.read()["xx"][0].canonical, "B");
+
+        assert!(reloadable.reload(&dir.join("missing")).is_err());
+        assert_eq!(reloadable.read()["xx"][0].canonical, "B");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
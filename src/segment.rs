@@ -0,0 +1,74 @@
+//! Word segmentation for CJK text, which has no spaces for word boundaries to be read from.
+//! With a `Segmenter`, tokens only match CJK text on segment boundaries, so a token doesn't
+//! fire on an accidental substring that straddles two words.
+
+use std::collections::HashSet;
+
+pub trait Segmenter {
+    /// The byte offsets in `text` where one segment ends and the next begins, in ascending
+    /// order. `0` and `text.len()` are always boundaries and needn't be included.
+    fn boundaries(&self, text: &str) -> Vec<usize>;
+}
+
+/// A forward maximum-matching segmenter: at each position it takes the longest dictionary
+/// word that starts there, or a single character when none does.
+#[derive(Debug, Default, Clone)]
+pub struct DictionarySegmenter {
+    words: HashSet<String>,
+    longest: usize,
+}
+
+impl DictionarySegmenter {
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>
+    {
+        let words: HashSet<String> = words.into_iter().map(Into::into).collect();
+        let longest = words.iter().map(|word| word.chars().count()).max().unwrap_or(0);
+        DictionarySegmenter { words, longest }
+    }
+}
+
+impl Segmenter for DictionarySegmenter {
+    fn boundaries(&self, text: &str) -> Vec<usize> {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let offset = |i: usize| chars.get(i).map_or(text.len(), |(offset, _)| *offset);
+        let mut boundaries = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let len = (2..=self.longest.min(chars.len() - i)).rev()
+                .find(|len| self.words.contains(&text[offset(i)..offset(i + len)]))
+                .unwrap_or(1);
+            i += len;
+            boundaries.push(offset(i));
+        }
+        boundaries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::token;
+    use crate::Tokenizer;
+
+    #[test]
+    fn test_dictionary_segmenter() {
+        let segmenter = DictionarySegmenter::new(vec!["南京", "大街", "光大", "街道"]);
+        assert_eq!(segmenter.boundaries("南京大街"), vec![6, 12]);
+        assert_eq!(segmenter.boundaries("光大街道"), vec![6, 12]);
+        assert_eq!(segmenter.boundaries("路"), vec![3]);
+    }
+
+    #[test]
+    fn test_tokenize_segmented() {
+        let tokens = vec![token(r#"{ "tokens": ["大街", "街"], "full": "大街", "canonical": "街", "skipBoundaries": true }"#)];
+        let tokenizer = Tokenizer::new(&tokens);
+        let segmenter = DictionarySegmenter::new(vec!["南京", "大街", "光大", "街道"]);
+
+        assert_eq!(tokenizer.tokenize_segmented("南京大街", &segmenter), "南京街");
+        assert_eq!(tokenizer.tokenize_segmented("光大街道", &segmenter), "光大街道");
+        assert_eq!(tokenizer.tokenize("光大街道"), "光街道");
+    }
+}
@@ -0,0 +1,8 @@
+//! Helpers shared by the unit tests of several modules.
+
+use crate::{InToken, Token};
+
+/// Builds a token from one group of token JSON, as if it had been loaded without a language.
+pub(crate) fn token(json: &str) -> Token {
+    Token::new(serde_json::from_str::<InToken>(json).unwrap()).unwrap()
+}
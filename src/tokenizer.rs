@@ -0,0 +1,1695 @@
+use crate::matcher::{near_misses, ExactMatcher, MatchContext, Matcher, Prepared};
+use crate::normalize::{is_combining_mark, normalize, NormalizeOptions};
+use crate::{Direction, Error, Token, TokenType};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TokenMatch {
+    pub start: usize,
+    pub end: usize,
+    /// The matched token's position in `Tokenizer::tokens`. For a tokenizer built with
+    /// `new` that is also its position in the original slice, see `token_at`.
+    pub token_index: usize,
+    pub replacement: String,
+    /// The matched token's type, filled in by the `Tokenizer`.
+    pub token_type: Option<TokenType>,
+}
+
+impl TokenMatch {
+    pub fn new(start: usize, end: usize, token_index: usize, replacement: String) -> Self {
+        TokenMatch {
+            start,
+            end,
+            token_index,
+            replacement,
+            token_type: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MatchOptions {
+    /// Replaces the spaces in multi-word replacements, e.g. `Some('_')` emits "New_York".
+    pub canonical_separator: Option<char>,
+    /// The edit distance within which `FuzzyMatcher` lets a word match a token form.
+    /// Only read by `FuzzyMatcher`, which requires the `fuzzy` feature.
+    pub max_edit_distance: Option<u8>,
+    /// Upper-cases the first letter of every word of the output, e.g. "ft st" becomes
+    /// "Ft St". The rest of each word is left alone, so canonicals such as "NW" keep their casing.
+    pub title_case_output: bool,
+    /// Leaves whitespace-delimited chunks that look like URLs or email addresses untouched,
+    /// so "info@main-street.com" doesn't become "info@main-st.com".
+    pub skip_urls_emails: bool,
+    /// Phrases no token may match inside of, e.g. "Kings Highway" when "Highway" is part of
+    /// the name. Occurrences are found verbatim.
+    pub protected_phrases: Vec<String>,
+    /// Clean-up applied to the input by `tokenize` and friends before matching. `find_tokens`
+    /// matches the text as given, so its offsets always refer to the caller's string.
+    pub normalize: NormalizeOptions,
+    /// Whether matches are rewritten to their abbreviated or their full form. Regex tokens
+    /// only describe abbreviations, so `ExactMatcher` ignores them when expanding.
+    pub direction: Direction,
+    /// Ignores matches spanning fewer characters than this, whatever the token. A token's own
+    /// `min_length`/`max_length` still apply on top, so the stricter bound wins.
+    pub min_token_len: usize,
+    /// Keeps only the first (leftmost) this many matches, leaving the rest of the text as is.
+    pub max_replacements: Option<usize>,
+    /// Leaves numbers with inner separators untouched, such as decimals and coordinates
+    /// ("40.7128", "-74.0060") or dates ("2024-01-15", "15/01/2024", "12:30").
+    pub skip_numeric_spans: bool,
+    /// Only matches that end the text, up to trailing punctuation and whitespace, are
+    /// applied, e.g. the "Street" of "Main Street" but not that of "Street Fighter".
+    pub last_word_only: bool,
+    /// Carries the casing of each matched span over to its replacement: an all-upper match
+    /// ("STREET") gives an all-upper replacement ("ST"), an all-lower one an all-lower
+    /// replacement, and a title-cased one a replacement with each word's first letter
+    /// upper-cased. The pattern is transferred as a whole rather than letter by letter, so
+    /// it doesn't matter that the replacement is shorter or longer than the match; a span
+    /// with any other casing ("StReEt", or a lone capital like "N") leaves the replacement
+    /// as the token spells it.
+    pub preserve_case: bool,
+}
+
+/// Why a candidate match wasn't applied.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Rejection {
+    /// The match covers no text.
+    Empty,
+    /// A word character sits right before or after the match, and the token doesn't set
+    /// `skip_boundaries`.
+    Boundary,
+    /// The token sets `requires_number` and no number is next to the match.
+    RequiresNumber,
+    /// The match is outside the token's `min_length`/`max_length`, or crosses more word
+    /// boundaries than its `span_boundaries` allows.
+    Length,
+    /// The match is shorter than `MatchOptions::min_token_len`.
+    MinTokenLen,
+    /// `MatchOptions::last_word_only` is set and words follow the match.
+    LastWordOnly,
+    /// The text matches one of the token's forms only when ignoring case, and the token
+    /// isn't `case_insensitive`.
+    CaseSensitive,
+    /// The text matches one of the token's forms only when ignoring diacritics (or "ß" and
+    /// "ss"), and the token sets `skip_diacritic_stripping`.
+    Diacritics,
+    /// A caller-supplied check, such as `tokenize_segmented`'s segment boundaries, refused it.
+    Filtered,
+    /// The match overlaps a URL, email address, numeric span or protected phrase.
+    Masked,
+    /// A match with precedence over it covers part of the same text.
+    Overlapped,
+    /// `MatchOptions::max_replacements` matches were applied before it. Only the first such
+    /// candidate is reported; the ones after it aren't examined.
+    MaxReplacements,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Decision {
+    pub candidate: TokenMatch,
+    /// `None` when the match was applied.
+    pub rejection: Option<Rejection>,
+}
+
+/// A trace of `Tokenizer::tokenize_diagnostic`: its output, and every candidate match in
+/// precedence order with what became of it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostics {
+    pub output: String,
+    pub decisions: Vec<Decision>,
+}
+
+/// What a `Tokenizer` was built from, for diagnosing matching problems. Regex tokens that
+/// don't compile never make it into a token set; `prepare_report` lists those.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TokenizerDebug {
+    /// Plain-string tokens, and the non-empty surface forms they match between them.
+    pub literal_tokens: usize,
+    pub literal_forms: usize,
+    /// Tokens with a compiled regex.
+    pub regex_tokens: usize,
+    /// Regex tokens of the tokenizer's languages that were left out because they don't
+    /// compile, as `prepare_report` finds them. Tokens without a language add nothing.
+    pub filtered_regex_tokens: usize,
+}
+
+/// Applies a set of tokens to input text, replacing every matched surface form with the
+/// token's canonical form (or its full form when the token sets `prefer_full`).
+///
+/// Candidate matches come from the tokenizer's `Matcher`. When several overlap, the leftmost
+/// one wins; among matches starting at the same byte the longest wins; remaining ties go to
+/// the token listed first.
+pub struct Tokenizer<'a, M = ExactMatcher> {
+    tokens: Vec<&'a Token>,
+    languages: Vec<Option<&'a str>>,
+    options: MatchOptions,
+    matcher: M,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(tokens: &'a [Token]) -> Self {
+        Tokenizer::with_options(tokens, MatchOptions::default())
+    }
+
+    pub fn with_options(tokens: &'a [Token], options: MatchOptions) -> Self {
+        Tokenizer {
+            tokens: tokens.iter().collect(),
+            languages: vec![None; tokens.len()],
+            options,
+            matcher: ExactMatcher,
+        }
+    }
+
+    /// Builds a tokenizer that also applies the language's own matching and output rules, e.g.
+    /// treating "ß" and "ss" as equivalent for `de`. Follow it with `with_match_options` for
+    /// other options; `with_options` would drop the language.
+    pub fn for_language(lc: &'a str, tokens: &'a [Token]) -> Self {
+        Tokenizer {
+            languages: vec![Some(lc); tokens.len()],
+            ..Tokenizer::new(tokens)
+        }
+    }
+
+    /// Like `for_language`, but each token gets the language it was loaded from, e.g. on the
+    /// tokens `merge` returns. Tokens without one get no language rules.
+    pub fn with_token_languages(tokens: &'a [Token]) -> Self {
+        Tokenizer {
+            languages: tokens.iter().map(|tk| tk.language.as_deref()).collect(),
+            ..Tokenizer::new(tokens)
+        }
+    }
+
+    /// Builds a tokenizer over several languages of a config. Languages listed first take
+    /// precedence when tokens from different languages tie.
+    pub fn from_config(config: &'a HashMap<String, Vec<Token>>, lcs: &[&str]) -> Result<Self, Error> {
+        let mut tokenizer = Tokenizer::with_options(&[], MatchOptions::default());
+        for lc in lcs {
+            let (lc, tokens) = config.get_key_value(*lc)
+                .ok_or_else(|| Error::LanguageCodeNotSupported(lc.to_string()))?;
+            tokenizer.tokens.extend(tokens.iter());
+            tokenizer.languages.extend(std::iter::repeat_n(Some(lc.as_str()), tokens.len()));
+        }
+        Ok(tokenizer)
+    }
+}
+
+impl<'a, M: Matcher> Tokenizer<'a, M> {
+    /// Swaps in a different matching strategy, keeping the tokens and options.
+    pub fn with_matcher<N: Matcher>(self, mut matcher: N) -> Tokenizer<'a, N> {
+        matcher.prepare(&self.tokens);
+        Tokenizer {
+            tokens: self.tokens,
+            languages: self.languages,
+            options: self.options,
+            matcher,
+        }
+    }
+
+    /// Replaces the options, keeping the tokens, their languages and the matcher, so that
+    /// language-aware tokenizers from `for_language` or `from_config` can be given options
+    /// such as `title_case_output` too.
+    pub fn with_match_options(self, options: MatchOptions) -> Self {
+        Tokenizer {
+            options,
+            ..self
+        }
+    }
+
+    pub fn debug_info(&self) -> TokenizerDebug {
+        let literals = self.tokens.iter().filter(|tk| !tk.regex);
+        let mut languages: Vec<&str> = self.languages.iter().flatten().copied().collect();
+        languages.sort_unstable();
+        languages.dedup();
+        TokenizerDebug {
+            literal_tokens: literals.clone().count(),
+            literal_forms: literals.flat_map(|tk| tk.tokens.iter()).filter(|form| !form.is_empty()).count(),
+            regex_tokens: self.tokens.iter().filter(|tk| tk.regex).count(),
+            filtered_regex_tokens: languages.into_iter().map(crate::filtered_regex_count).sum(),
+        }
+    }
+
+    pub fn tokens(&self) -> &[&'a Token] {
+        &self.tokens
+    }
+
+    pub fn language(&self, token_index: usize) -> Option<&'a str> {
+        self.languages.get(token_index).copied().flatten()
+    }
+
+    /// Returns the token that matches the whole of `word`, along with the language it was
+    /// loaded from. Tokens added without a language are never returned.
+    pub fn find_with_language(&self, word: &str) -> Option<(&'a str, &'a Token)> {
+        self.find_tokens(word)
+            .into_iter()
+            .find(|m| m.start == 0 && m.end == word.len())
+            .and_then(|m| Some((self.language(m.token_index)?, self.tokens[m.token_index])))
+    }
+
+    pub fn find_tokens(&self, text: &str) -> Vec<TokenMatch> {
+        self.find_tokens_where(text, |_| true)
+    }
+
+    /// Like `tokenize`, but CJK matches must start and end on a boundary found by
+    /// `segmenter`, even for tokens that set `skip_boundaries`.
+    #[cfg(feature = "cjk")]
+    pub fn tokenize_segmented<S: crate::Segmenter>(&self, text: &str, segmenter: &S) -> String {
+        let text = normalize(text, &self.options.normalize);
+        let mut boundaries = segmenter.boundaries(&text);
+        boundaries.push(0);
+        boundaries.sort_unstable();
+        let matches = self.find_tokens_where(&text, |m| {
+            !is_cjk_span(&text[m.start..m.end])
+                || (boundaries.binary_search(&m.start).is_ok() && boundaries.binary_search(&m.end).is_ok())
+        });
+        self.finish(apply(&text, &matches))
+    }
+
+    // Finds the matches of `text`, considering only candidates `keep` accepts on top of the
+    // usual boundary and length checks.
+    fn find_tokens_where(&self, text: &str, keep: impl Fn(&TokenMatch) -> bool) -> Vec<TokenMatch> {
+        self.decide(text, &keep)
+            .into_iter()
+            .filter(|d| d.rejection.is_none())
+            .map(|d| d.candidate)
+            .collect()
+    }
+
+    // Every candidate match in precedence order, with the reason it isn't applied if so.
+    // Stops at the first candidate past `max_replacements`.
+    fn decide(&self, text: &str, keep: &dyn Fn(&TokenMatch) -> bool) -> Vec<Decision> {
+        let (candidates, masked) = self.sorted_candidates(text);
+        let mut decisions = Vec::with_capacity(candidates.len());
+        let mut last_end = None;
+        let mut applied = 0;
+        for mut m in candidates {
+            let rejection = self.rejection(text, &m, &masked, keep).or_else(|| {
+                if last_end.is_some_and(|end| m.start < end) {
+                    Some(Rejection::Overlapped)
+                } else if self.options.max_replacements.is_some_and(|max| applied >= max) {
+                    Some(Rejection::MaxReplacements)
+                } else {
+                    None
+                }
+            });
+            if rejection.is_none() {
+                applied += 1;
+                last_end = Some(m.end);
+                if let Some(separator) = self.options.canonical_separator {
+                    m.replacement = m.replacement.replace(' ', separator.encode_utf8(&mut [0; 4]));
+                }
+                if self.options.preserve_case {
+                    m.replacement = transfer_case(&text[m.start..m.end], &m.replacement);
+                }
+            }
+            let done = rejection == Some(Rejection::MaxReplacements);
+            decisions.push(Decision { candidate: m, rejection });
+            if done {
+                break;
+            }
+        }
+        decisions
+    }
+
+    // Every acceptable candidate match, possibly overlapping, in precedence order.
+    fn candidates(&self, text: &str, keep: impl Fn(&TokenMatch) -> bool) -> Vec<TokenMatch> {
+        self.traced_candidates(text, &keep)
+            .into_iter()
+            .filter(|d| d.rejection.is_none())
+            .map(|d| d.candidate)
+            .collect()
+    }
+
+    // Every candidate match the matcher proposed, in precedence order, along with the check
+    // it failed if any.
+    fn traced_candidates(&self, text: &str, keep: &dyn Fn(&TokenMatch) -> bool) -> Vec<Decision> {
+        let (candidates, masked) = self.sorted_candidates(text);
+        candidates.into_iter()
+            .map(|m| Decision {
+                rejection: self.rejection(text, &m, &masked, keep),
+                candidate: m,
+            })
+            .collect()
+    }
+
+    // Every candidate match the matcher proposed, in precedence order, along with the spans
+    // no match may overlap.
+    fn sorted_candidates(&self, text: &str) -> (Vec<TokenMatch>, Vec<(usize, usize)>) {
+        let mut candidates = self.matcher.find_prepared(text, &self.context(), Prepared(()));
+        candidates.retain(|m| m.token_index < self.tokens.len());
+        for m in &mut candidates {
+            m.token_type = self.tokens[m.token_index].token_type;
+        }
+        candidates.sort_by(precedence);
+        (candidates, self.masked_spans(text))
+    }
+
+    fn context(&self) -> MatchContext<'_> {
+        MatchContext {
+            tokens: &self.tokens,
+            languages: &self.languages,
+            options: &self.options,
+        }
+    }
+
+    // The spans no match may overlap.
+    fn masked_spans(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut masked = Vec::new();
+        if self.options.skip_urls_emails {
+            masked.extend(url_email_spans(text));
+        }
+        if self.options.skip_numeric_spans {
+            masked.extend(numeric_spans(text));
+        }
+        for phrase in self.options.protected_phrases.iter().filter(|phrase| !phrase.is_empty()) {
+            masked.extend(text.match_indices(phrase.as_str()).map(|(start, p)| (start, start + p.len())));
+        }
+        masked
+    }
+
+    fn rejection(
+        &self,
+        text: &str,
+        m: &TokenMatch,
+        masked: &[(usize, usize)],
+        keep: &dyn Fn(&TokenMatch) -> bool,
+    ) -> Option<Rejection> {
+        let tk = self.tokens[m.token_index];
+        if m.end <= m.start {
+            Some(Rejection::Empty)
+        } else if !tk.skip_boundaries && !is_boundary(text, m.start, m.end) {
+            Some(Rejection::Boundary)
+        } else if tk.requires_number && !next_to_number(text, m.start, m.end) {
+            Some(Rejection::RequiresNumber)
+        } else if !within_length(tk, &text[m.start..m.end]) {
+            Some(Rejection::Length)
+        } else if text[m.start..m.end].chars().count() < self.options.min_token_len {
+            Some(Rejection::MinTokenLen)
+        } else if self.options.last_word_only && text[m.end..].chars().any(is_word_char) {
+            Some(Rejection::LastWordOnly)
+        } else if !keep(m) {
+            Some(Rejection::Filtered)
+        } else if masked.iter().any(|&(start, end)| m.start < end && start < m.end) {
+            Some(Rejection::Masked)
+        } else {
+            None
+        }
+    }
+
+    /// Like `tokenize`, but also returns every candidate match the matcher proposed and,
+    /// for those that weren't applied, why. Spans a plain-string token would have matched
+    /// but for case or diacritics are included too, as `CaseSensitive` and `Diacritics`
+    /// rejections. Offsets refer to the normalized input.
+    pub fn tokenize_diagnostic(&self, text: &str) -> Diagnostics {
+        let text = normalize(text, &self.options.normalize);
+        let mut decisions = self.decide(&text, &|_| true);
+        let scanned = match decisions.last() {
+            Some(d) if d.rejection == Some(Rejection::MaxReplacements) => d.candidate.start,
+            _ => text.len()
+        };
+        let masked = self.masked_spans(&text);
+        for (mut m, rejection) in near_misses(&text, &self.context()) {
+            if m.start < scanned && self.rejection(&text, &m, &masked, &|_| true).is_none() {
+                m.token_type = self.tokens[m.token_index].token_type;
+                decisions.push(Decision { candidate: m, rejection: Some(rejection) });
+            }
+        }
+        decisions.sort_by(|a, b| precedence(&a.candidate, &b.candidate));
+        let matches: Vec<TokenMatch> = decisions.iter()
+            .filter(|d| d.rejection.is_none())
+            .map(|d| d.candidate.clone())
+            .collect();
+        Diagnostics {
+            output: self.finish(apply(&text, &matches)),
+            decisions,
+        }
+    }
+
+    pub fn tokenize(&self, text: &str) -> String {
+        let text = normalize(text, &self.options.normalize);
+        self.finish(apply(&text, &self.find_tokens(&text)))
+    }
+
+    // Like `tokenize`, but writes the output to `out`, which is cleared first, so a caller
+    // tokenizing many strings can keep reusing one allocation.
+    fn tokenize_into(&self, text: &str, out: &mut String) {
+        let text = normalize(text, &self.options.normalize);
+        out.clear();
+        apply_into(&text, &self.find_tokens(&text), out);
+        if self.options.title_case_output {
+            *out = self.finish(std::mem::take(out));
+        }
+    }
+
+    /// Like `tokenize`, but each match is replaced with whatever `f` returns for the matched
+    /// token and the text it matched, instead of the token's replacement.
+    pub fn tokenize_with<F: Fn(&Token, &str) -> String>(&self, text: &str, f: F) -> String {
+        let text = normalize(text, &self.options.normalize);
+        let mut matches = self.find_tokens(&text);
+        for m in &mut matches {
+            m.replacement = f(self.tokens[m.token_index], &text[m.start..m.end]);
+        }
+        self.finish(apply(&text, &matches))
+    }
+
+    /// Like `tokenize`, but borrows `text` instead of allocating when it comes out unchanged.
+    pub fn tokenize_cow<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        let normalized = normalize(text, &self.options.normalize);
+        let matches = self.find_tokens(&normalized);
+        if matches.is_empty() && !self.options.title_case_output {
+            return normalized;
+        }
+        let out = self.finish(apply(&normalized, &matches));
+        if out == text {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(out)
+        }
+    }
+
+    /// Like `tokenize`, but also returns the words that look like street types no token
+    /// matched. A word counts as a candidate when it is the last word of a comma- or
+    /// semicolon-separated segment and starts with an upper-case letter, e.g. "Promenade" in
+    /// "12 Ocean Promenade, Brighton" (so is "Brighton").
+    pub fn tokenize_with_misses(&self, text: &str) -> (String, Vec<String>) {
+        let text = normalize(text, &self.options.normalize);
+        let matches = self.find_tokens(&text);
+        let mut misses = Vec::new();
+        let mut offset = 0;
+        for segment in text.split([',', ';']) {
+            let trimmed = segment.trim_end_matches(|c: char| !is_word_char(c));
+            let start = trimmed.char_indices().rev()
+                .find(|(_, c)| !is_word_char(*c))
+                .map_or(0, |(i, c)| i + c.len_utf8());
+            let word = &trimmed[start..];
+            let (word_start, word_end) = (offset + start, offset + trimmed.len());
+            offset += segment.len() + 1;
+            if !word.chars().next().is_some_and(char::is_uppercase) {
+                continue;
+            }
+            if !matches.iter().any(|m| m.start < word_end && word_start < m.end) {
+                misses.push(word.to_string());
+            }
+        }
+        (self.finish(apply(&text, &matches)), misses)
+    }
+
+    /// Like `tokenize`, but also returns a confidence in `[0, 1]` for the result.
+    ///
+    /// Each replacement is weighted as `0.5 * literal + 0.25 * !prefer_full + 0.25 * typed`,
+    /// where each term is 1 when the token is a plain-string token, does not set
+    /// `prefer_full`, or has a `token_type` respectively, and 0 otherwise. The aggregate is
+    /// the mean weight over all replacements, or 1.0 when nothing was replaced.
+    pub fn tokenize_scored(&self, text: &str) -> (String, f32) {
+        let text = normalize(text, &self.options.normalize);
+        let matches = self.find_tokens(&text);
+        let score = if matches.is_empty() {
+            1.0
+        } else {
+            let total: f32 = matches.iter().map(|m| weight(self.tokens[m.token_index])).sum();
+            total / matches.len() as f32
+        };
+        (self.finish(apply(&text, &matches)), score)
+    }
+
+    fn finish(&self, out: String) -> String {
+        if self.options.title_case_output {
+            let particles = if self.languages.contains(&Some("nl")) { DUTCH_PARTICLES } else { &[] };
+            title_case(&out, particles)
+        } else {
+            out
+        }
+    }
+}
+
+/// Groups the spans of `text` matched by typed tokens under their token's type, in the order
+/// they appear. Untyped matches are left out.
+pub fn extract_typed(text: &str, tokens: &[Token]) -> HashMap<TokenType, Vec<String>> {
+    let mut components: HashMap<TokenType, Vec<String>> = HashMap::new();
+    for m in Tokenizer::new(tokens).find_tokens(text) {
+        if let Some(token_type) = m.token_type {
+            components.entry(token_type).or_default().push(text[m.start..m.end].to_string());
+        }
+    }
+    components
+}
+
+/// Abbreviates the cardinal directions in `input` with only the `Cardinal` tokens in
+/// `tokens`, leaving the rest of the text alone: "North Main Street" becomes "N Main
+/// Street". Two directions written as separate words, as in "North West" or "North-West",
+/// collapse into one where the tokens have a compound direction spelled as their canonicals
+/// joined, so they give "NW" rather than "N W".
+///
+/// Of the built-in languages, cs, en, fr, nl, sk and sv have cardinal tokens; only en
+/// ("NW") and fr ("NO") have compound ones.
+pub fn normalize_cardinals(input: &str, tokens: &[Token]) -> String {
+    let cardinals: Vec<Token> = tokens.iter()
+        .filter(|tk| tk.token_type == Some(TokenType::Cardinal))
+        .cloned()
+        .collect();
+    let tokenizer = Tokenizer::new(&cardinals);
+    let text = normalize(input, &tokenizer.options.normalize);
+
+    let mut matches: Vec<TokenMatch> = Vec::new();
+    for m in tokenizer.find_tokens(&text) {
+        if let Some(last) = matches.last_mut() {
+            let between = &text[last.end..m.start];
+            let compound = format!("{}{}", last.replacement, m.replacement);
+            if !between.is_empty() && between.chars().all(|c| c.is_whitespace() || c == '-')
+                && cardinals.iter().any(|tk| tk.canonical == compound) {
+                last.end = m.end;
+                last.replacement = compound;
+                continue;
+            }
+        }
+        matches.push(m);
+    }
+    tokenizer.finish(apply(&text, &matches))
+}
+
+/// Tokenizes field `col` of a CSV record and copies the other fields through unchanged. When
+/// the record has no such column it is returned as is.
+pub fn abbreviate_csv_column(record: &[&str], col: usize, tokens: &[Token]) -> Vec<String> {
+    let tokenizer = Tokenizer::new(tokens);
+    record.iter().enumerate().map(|(i, field)| {
+        if i == col {
+            tokenizer.tokenize(field)
+        } else {
+            field.to_string()
+        }
+    }).collect()
+}
+
+/// Abbreviates `input` with `tokens`: plain-string tokens replace any of their surface forms
+/// with their canonical form (or leave their full form when they set `prefer_full`), regex
+/// tokens replace their pattern's matches with their canonical template. Matches must sit on
+/// word boundaries unless the token sets `skip_boundaries`.
+///
+/// When matches overlap, the leftmost wins, then the longest, then the token listed first.
+pub fn abbreviate(input: &str, tokens: &[Token]) -> String {
+    Tokenizer::new(tokens).tokenize(input)
+}
+
+/// One replacement made by `abbreviate_with_spans`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Replacement {
+    /// The byte range of the match in the input.
+    pub start: usize,
+    pub end: usize,
+    /// The input text that matched.
+    pub matched: String,
+    /// What it was replaced with.
+    pub replacement: String,
+    /// The matched token's position in `tokens`.
+    pub token_index: usize,
+}
+
+/// Like `abbreviate`, but also returns each replacement made, in input order.
+pub fn abbreviate_with_spans(input: &str, tokens: &[Token]) -> (String, Vec<Replacement>) {
+    let tokenizer = Tokenizer::new(tokens);
+    let matches = tokenizer.find_tokens(input);
+    let replacements = matches.iter()
+        .map(|m| Replacement {
+            start: m.start,
+            end: m.end,
+            matched: input[m.start..m.end].to_string(),
+            replacement: m.replacement.clone(),
+            token_index: m.token_index,
+        })
+        .collect();
+    (tokenizer.finish(apply(input, &matches)), replacements)
+}
+
+/// Abbreviates each of `inputs` as `abbreviate` would, setting up the tokenizer only once.
+pub fn abbreviate_batch(inputs: &[String], tokens: &[Token]) -> Vec<String> {
+    tokenize_batch(&Tokenizer::new(tokens), inputs)
+}
+
+// Tokenizes each of `inputs` through one scratch buffer, so each result is allocated once at
+// its final size.
+fn tokenize_batch(tokenizer: &Tokenizer, inputs: &[String]) -> Vec<String> {
+    let mut out = String::new();
+    inputs.iter()
+        .map(|input| {
+            tokenizer.tokenize_into(input, &mut out);
+            out.as_str().to_owned()
+        })
+        .collect()
+}
+
+/// Like `abbreviate_batch`, but splits `inputs` into one contiguous chunk per available CPU
+/// and abbreviates the chunks on scoped threads. The results keep the order of `inputs`.
+#[cfg(feature = "parallel")]
+pub fn abbreviate_batch_par(inputs: &[String], tokens: &[Token]) -> Vec<String> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if threads < 2 || inputs.len() < 2 {
+        return abbreviate_batch(inputs, tokens);
+    }
+    let tokenizer = Tokenizer::new(tokens);
+    let chunk = inputs.len().div_ceil(threads);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = inputs.chunks(chunk)
+            .map(|inputs| {
+                let tokenizer = &tokenizer;
+                scope.spawn(move || tokenize_batch(tokenizer, inputs))
+            })
+            .collect();
+        handles.into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
+/// Replaces every matched surface form in `input` with its token's full form, e.g. "Main St"
+/// becomes "Main Street". Where several tokens match the same span, as "St" does for both
+/// "Saint" and "Street" in English, the one listed first wins; see `expand_all` for all of
+/// them. Regex tokens are not applied.
+pub fn expand(input: &str, tokens: &[Token]) -> String {
+    Tokenizer::with_options(tokens, MatchOptions {
+        direction: Direction::Expand,
+        ..MatchOptions::default()
+    }).tokenize(input)
+}
+
+/// The most results `expand_all` returns.
+pub const MAX_EXPANSIONS: usize = 256;
+
+/// Like `expand`, but returns every combination of expansions of the ambiguous spans, with
+/// the one `expand` returns first. The number of combinations grows with the product of the
+/// alternatives per span, so only the first `MAX_EXPANSIONS` are returned, varying the last
+/// spans first.
+pub fn expand_all(input: &str, tokens: &[Token]) -> Vec<String> {
+    let tokenizer = Tokenizer::with_options(tokens, MatchOptions {
+        direction: Direction::Expand,
+        ..MatchOptions::default()
+    });
+    let candidates = tokenizer.candidates(input, |_| true);
+    let mut expansions = vec![String::new()];
+    let mut last = 0;
+    for m in tokenizer.find_tokens(input) {
+        let mut alternatives: Vec<&str> = Vec::new();
+        for candidate in candidates.iter().filter(|c| c.start == m.start && c.end == m.end) {
+            if !alternatives.contains(&candidate.replacement.as_str()) {
+                alternatives.push(&candidate.replacement);
+            }
+        }
+        let between = &input[last..m.start];
+        expansions = expansions.iter()
+            .flat_map(|prefix| alternatives.iter().map(move |alternative| format!("{}{}{}", prefix, between, alternative)))
+            .take(MAX_EXPANSIONS)
+            .collect();
+        last = m.end;
+    }
+    for expansion in &mut expansions {
+        expansion.push_str(&input[last..]);
+    }
+    expansions
+}
+
+/// Returns `text` along with its abbreviated and its expanded form, in that order and
+/// without duplicates, so up to three strings.
+pub fn variants(text: &str, tokens: &[Token]) -> Vec<String> {
+    let mut variants = vec![text.to_string()];
+    for variant in [Tokenizer::new(tokens).tokenize(text), expand(text, tokens)] {
+        if !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+    variants
+}
+
+/// Returns the languages of `config` whose tokens change `text` when abbreviating it, in
+/// code order. Unlike checking for any match, this leaves out languages whose matches only
+/// rewrite `text` to itself.
+pub fn changing_languages(text: &str, config: &HashMap<String, Vec<Token>>) -> Vec<String> {
+    let mut lcs: Vec<String> = config.iter()
+        .filter(|(lc, tokens)| Tokenizer::for_language(lc, tokens).tokenize(text) != text)
+        .map(|(lc, _)| lc.clone())
+        .collect();
+    lcs.sort();
+    lcs
+}
+
+fn weight(tk: &Token) -> f32 {
+    let mut weight = 0.0;
+    if !tk.regex {
+        weight += 0.5;
+    }
+    if !tk.prefer_full {
+        weight += 0.25;
+    }
+    if tk.token_type.is_some() {
+        weight += 0.25;
+    }
+    weight
+}
+
+fn apply(text: &str, matches: &[TokenMatch]) -> String {
+    let mut out = String::with_capacity(text.len());
+    apply_into(text, matches, &mut out);
+    out
+}
+
+fn apply_into(text: &str, matches: &[TokenMatch], out: &mut String) {
+    let mut last = 0;
+    for m in matches {
+        out.push_str(&text[last..m.start]);
+        out.push_str(&m.replacement);
+        last = m.end;
+    }
+    out.push_str(&text[last..]);
+}
+
+// Runs of digits joined by single separators, with at least one separator, e.g. "40.7128"
+// or "2024-01-15". A leading sign is part of the run.
+fn numeric_spans(text: &str) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let is_separator = |b: u8| matches!(b, b'.' | b',' | b':' | b'/' | b'-');
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let start = if i > 0 && matches!(bytes[i - 1], b'-' | b'+') { i - 1 } else { i };
+        let mut end = i;
+        let mut separated = false;
+        loop {
+            while end < bytes.len() && bytes[end].is_ascii_digit() {
+                end += 1;
+            }
+            if end + 1 < bytes.len() && is_separator(bytes[end]) && bytes[end + 1].is_ascii_digit() {
+                separated = true;
+                end += 1;
+            } else {
+                break;
+            }
+        }
+        if separated {
+            spans.push((start, end));
+        }
+        i = end;
+    }
+    spans
+}
+
+fn url_email_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut chunk_start = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        if !c.is_whitespace() {
+            chunk_start.get_or_insert(i);
+            continue;
+        }
+        let start = match chunk_start.take() {
+            Some(start) => start,
+            None => continue
+        };
+        let chunk = text[start..i].to_lowercase();
+        let url = chunk.contains("://") || chunk.starts_with("www.");
+        let email = chunk.split_once('@').is_some_and(|(user, domain)| {
+            !user.is_empty() && domain.trim_end_matches('.').contains('.')
+        });
+        if url || email {
+            spans.push((start, i));
+        }
+    }
+    spans
+}
+
+// Dutch tussenvoegsels, which stay lower-case in title-cased names like "van der Berg".
+const DUTCH_PARTICLES: &[&str] = &["aan", "bij", "de", "den", "der", "het", "in", "op", "te", "ten", "ter", "uit", "van"];
+
+fn title_case(text: &str, particles: &[&str]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(first) = rest.chars().next() {
+        if !is_word_char(first) {
+            out.push(first);
+            rest = &rest[first.len_utf8()..];
+            continue;
+        }
+        let len = rest.find(|c: char| !is_word_char(c) && c != '\'').unwrap_or(rest.len());
+        let word = &rest[..len];
+        let lower = word.to_lowercase();
+        if particles.contains(&lower.as_str()) {
+            out.push_str(&lower);
+        } else {
+            out.extend(first.to_uppercase());
+            out.push_str(&word[first.len_utf8()..]);
+        }
+        rest = &rest[len..];
+    }
+    out
+}
+
+#[derive(PartialEq)]
+enum Casing {
+    Upper,
+    Lower,
+    Title,
+}
+
+fn casing(span: &str) -> Option<Casing> {
+    let cased: Vec<char> = span.chars().filter(|c| c.is_uppercase() || c.is_lowercase()).collect();
+    if cased.len() > 1 && cased.iter().all(|c| c.is_uppercase()) {
+        return Some(Casing::Upper);
+    }
+    if !cased.is_empty() && cased.iter().all(|c| c.is_lowercase()) {
+        return Some(Casing::Lower);
+    }
+    let title = span.split(|c: char| !is_word_char(c)).filter(|word| !word.is_empty()).all(|word| {
+        let mut chars = word.chars();
+        chars.next().is_some_and(|c| !c.is_lowercase()) && chars.all(|c| !c.is_uppercase())
+    });
+    if title && cased.len() > 1 {
+        Some(Casing::Title)
+    } else {
+        None
+    }
+}
+
+fn transfer_case(span: &str, replacement: &str) -> String {
+    match casing(span) {
+        Some(Casing::Upper) => replacement.to_uppercase(),
+        Some(Casing::Lower) => replacement.to_lowercase(),
+        Some(Casing::Title) => title_case(replacement, &[]),
+        None => replacement.to_string()
+    }
+}
+
+// Combining marks belong to the word they follow, so "St" in a decomposed "Sté" isn't
+// followed by a boundary. Apostrophes stay boundaries so elided forms like "l'Avenue" match.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || is_combining_mark(c)
+}
+
+fn is_boundary(text: &str, start: usize, end: usize) -> bool {
+    let before = text[..start].chars().next_back();
+    let after = text[end..].chars().next();
+    !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+}
+
+// Earlier matches first, then longer ones, then those of earlier tokens.
+fn precedence(a: &TokenMatch, b: &TokenMatch) -> std::cmp::Ordering {
+    a.start.cmp(&b.start)
+        .then((b.end - b.start).cmp(&(a.end - a.start)))
+        .then(a.token_index.cmp(&b.token_index))
+}
+
+// Whether the nearest non-space characters on either side of the span include a digit.
+fn next_to_number(text: &str, start: usize, end: usize) -> bool {
+    let before = text[..start].trim_end().chars().next_back();
+    let after = text[end..].trim_start().chars().next();
+    before.is_some_and(|c| c.is_numeric()) || after.is_some_and(|c| c.is_numeric())
+}
+
+fn within_length(tk: &Token, span: &str) -> bool {
+    let len = span.chars().count();
+    if tk.min_length.is_some_and(|min| len < min) || tk.max_length.is_some_and(|max| len > max) {
+        return false;
+    }
+    // CJK text has no spaces to count boundaries by, so there `span_boundaries` caps the
+    // number of graphemes a match may cover instead.
+    // Elsewhere it caps the number of whitespace runs inside the match, so a token without
+    // it stays within a single word.
+    match tk.span_boundaries {
+        Some(n) if is_cjk_span(span) => grapheme_count(span) <= n as usize,
+        n => whitespace_runs(span) <= n.unwrap_or(0) as usize
+    }
+}
+
+fn whitespace_runs(span: &str) -> usize {
+    span.split_whitespace().count().saturating_sub(1)
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{11FF}' // Hangul Jamo
+        | '\u{3040}'..='\u{30FF}' // Hiragana, Katakana
+        | '\u{3130}'..='\u{318F}' // Hangul Compatibility Jamo
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}' // Hangul Syllables
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+        | '\u{20000}'..='\u{2FA1F}' // CJK Extensions B-F
+    )
+}
+
+fn is_cjk_span(span: &str) -> bool {
+    !span.chars().any(char::is_whitespace) && span.chars().any(is_cjk)
+}
+
+// Approximates extended grapheme clusters as a base character followed by any combining
+// marks, variation selectors or zero-width-joined characters.
+fn grapheme_count(span: &str) -> usize {
+    let mut count = 0;
+    let mut joined = false;
+    for c in span.chars() {
+        let extends = is_combining_mark(c) || matches!(c, '\u{200D}' | '\u{FE00}'..='\u{FE0F}');
+        if !extends && !joined {
+            count += 1;
+        }
+        joined = c == '\u{200D}';
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::token;
+    use crate::{config, config_with, literal_tokens, regex_tokens, ConfigOptions, Replacer};
+
+    #[test]
+    fn test_tokenize() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let tokenizer = Tokenizer::new(&map["en"]);
+
+        assert_eq!(tokenizer.tokenize("Main Street"), "Main St");
+        assert_eq!(tokenizer.tokenize("Streetsboro"), "Streetsboro");
+    }
+
+    #[test]
+    fn test_tokenize_cow() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let tokenizer = Tokenizer::new(&map["en"]);
+
+        assert!(matches!(tokenizer.tokenize_cow("Streetsboro"), Cow::Borrowed("Streetsboro")));
+        assert!(matches!(tokenizer.tokenize_cow("Main St"), Cow::Borrowed("Main St")));
+        assert_eq!(tokenizer.tokenize_cow("Main Street"), Cow::<str>::Owned(String::from("Main St")));
+    }
+
+    #[test]
+    fn test_abbreviate_csv_column() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let record = ["12", "Main Street", "Main Street", "US"];
+
+        assert_eq!(abbreviate_csv_column(&record, 2, &map["en"]), vec!["12", "Main Street", "Main St", "US"]);
+        assert_eq!(abbreviate_csv_column(&record, 4, &map["en"]), record.to_vec());
+    }
+
+    #[test]
+    fn test_abbreviate() {
+        let map = config(vec![String::from("en")]).unwrap();
+
+        assert_eq!(abbreviate("Main Street", &map["en"]), "Main St");
+        assert_eq!(abbreviate("Mainstreet", &map["en"]), "Mainstreet");
+        assert_eq!(abbreviate("Main Street suite 5", &map["en"]), "Main St ");
+        assert_eq!(abbreviate("Northwest Road", &map["en"]), "NW Rd");
+        assert_eq!(abbreviate("Ridge Road", &map["en"]), "Ridge Rd");
+    }
+
+    #[test]
+    fn test_prefer_full() {
+        let map = config(vec![String::from("en")]).unwrap();
+
+        assert_eq!(abbreviate("Texas State University", &map["en"]), "Texas State University");
+        assert_eq!(abbreviate("Texas State Univ", &map["en"]), "Texas State University");
+        assert_eq!(expand("Texas State Univ", &map["en"]), "Texas State University");
+        assert_eq!(abbreviate("Rdg Road", &map["en"]), "Ridge Rd");
+    }
+
+    #[test]
+    fn test_normalize_cardinals() {
+        let map = config(vec![String::from("en"), String::from("fr")]).unwrap();
+        let en = &map["en"];
+
+        assert_eq!(normalize_cardinals("North Main Street", en), "N Main Street");
+        assert_eq!(normalize_cardinals("N Main Street", en), "N Main Street");
+        assert_eq!(normalize_cardinals("Main Street Southwest", en), "Main Street SW");
+        assert_eq!(normalize_cardinals("Main Street SW", en), "Main Street SW");
+        assert_eq!(normalize_cardinals("North West Road", en), "NW Road");
+        assert_eq!(normalize_cardinals("North-West Road", en), "NW Road");
+        assert_eq!(normalize_cardinals("N W Road", en), "NW Road");
+        assert_eq!(normalize_cardinals("North South Road", en), "N S Road");
+        assert_eq!(normalize_cardinals("Rue Nord Ouest", &map["fr"]), "Rue NO");
+        assert_eq!(normalize_cardinals("Northgate Avenue", en), "Northgate Avenue");
+    }
+
+    #[test]
+    fn test_expand() {
+        let map = config(vec![String::from("en")]).unwrap();
+
+        assert_eq!(expand("Main Ave", &map["en"]), "Main Avenue");
+        assert_eq!(expand("Main St", &map["en"]), "Main Saint");
+
+        let all = expand_all("Main St", &map["en"]);
+        assert_eq!(all[0], "Main Saint");
+        assert!(all.contains(&String::from("Main Street")));
+        assert_eq!(expand_all("Ft St", &map["en"]).len(), all.len() * expand_all("Ft", &map["en"]).len());
+        assert_eq!(expand_all("Springfield", &map["en"]), vec!["Springfield"]);
+
+        let many = ["St"; 12].join(" ");
+        let all = expand_all(&many, &map["en"]);
+        assert_eq!(all.len(), MAX_EXPANSIONS);
+        assert_eq!(all[0], expand(&many, &map["en"]));
+        assert!(all.iter().all(|expansion| !expansion.contains("St ") && !expansion.ends_with("St")));
+    }
+
+    #[test]
+    fn test_variants() {
+        let map = config(vec![String::from("en")]).unwrap();
+
+        assert_eq!(variants("Fort Ave", &map["en"]), vec!["Fort Ave", "Ft Av", "Fort Avenue"]);
+        assert_eq!(variants("Fort Street", &map["en"]), vec!["Fort Street", "Ft St"]);
+        assert_eq!(variants("Springfield", &map["en"]), vec!["Springfield"]);
+    }
+
+    #[test]
+    fn test_tokenize_with_misses() {
+        let tokens = vec![token(r#"{ "tokens": ["St", "Street"], "full": "Street", "canonical": "St" }"#)];
+        let tokenizer = Tokenizer::new(&tokens);
+
+        assert_eq!(tokenizer.tokenize_with_misses("12 Main Street; 3 Ocean Promenade, apt 4"), (
+            String::from("12 Main St; 3 Ocean Promenade, apt 4"),
+            vec![String::from("Promenade")]
+        ));
+        assert_eq!(tokenizer.tokenize_with_misses(""), (String::new(), Vec::new()));
+    }
+
+    #[test]
+    fn test_debug_info() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let info = Tokenizer::new(&map["en"]).debug_info();
+
+        assert_eq!(info.literal_tokens, literal_tokens(&map["en"]).len());
+        assert_eq!(info.regex_tokens, regex_tokens(&map["en"]).len());
+        assert_eq!(info.literal_tokens + info.regex_tokens, map["en"].len());
+        assert!(info.regex_tokens > 0);
+        assert!(info.literal_forms > info.literal_tokens);
+        assert_eq!(info.filtered_regex_tokens, 0);
+
+        crate::register_language("zr", r##"[
+            { "tokens": ["Street", "St"], "full": "Street", "canonical": "St" },
+            { "tokens": ["", "(?<=\\d+ )Apt"], "full": "(?<=\\d+ )Apt", "canonical": "#", "regex": true },
+            { "tokens": ["", "(Unit"], "full": "(Unit", "canonical": "#", "regex": true }
+        ]"##);
+        let report = crate::prepare_report(vec![String::from("zr"), String::from("zr-US")]).unwrap();
+        assert_eq!(Tokenizer::new(&report.tokens["zr"]).debug_info().filtered_regex_tokens, 0);
+        let info = Tokenizer::for_language("zr", &report.tokens["zr"]).debug_info();
+        assert_eq!((info.literal_tokens, info.regex_tokens, info.filtered_regex_tokens), (1, 0, 2));
+        let info = Tokenizer::from_config(&report.tokens, &["zr", "zr-US"]).unwrap().debug_info();
+        assert_eq!(info.filtered_regex_tokens, 4);
+    }
+
+    #[test]
+    fn test_tokenize_scored() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let tokenizer = Tokenizer::new(&map["en"]);
+
+        let (literal, literal_score) = tokenizer.tokenize_scored("Main Street");
+        assert_eq!(literal, "Main St");
+        let (regex, regex_score) = tokenizer.tokenize_scored("suite 5");
+        assert_eq!(regex, "");
+        assert!(literal_score > regex_score);
+
+        assert_eq!(tokenizer.tokenize_scored("Main"), (String::from("Main"), 1.0));
+    }
+
+    #[test]
+    fn test_title_case_output() {
+        let tokens = vec![
+            token(r#"{ "tokens": ["fort", "ft"], "full": "fort", "canonical": "ft" }"#),
+            token(r#"{ "tokens": ["street", "st"], "full": "street", "canonical": "st" }"#),
+            token(r#"{ "tokens": ["northwest", "NW"], "full": "northwest", "canonical": "NW" }"#),
+        ];
+        let tokenizer = Tokenizer::with_options(&tokens, MatchOptions {
+            title_case_output: true,
+            ..MatchOptions::default()
+        });
+
+        assert_eq!(tokenizer.tokenize("fort street"), "Ft St");
+        assert_eq!(tokenizer.tokenize("o'malley street northwest"), "O'malley St NW");
+        assert_eq!(Tokenizer::new(&tokens).tokenize("fort street"), "ft st");
+    }
+
+    #[test]
+    fn test_preserve_case() {
+        let options = ConfigOptions::new().languages(vec![String::from("en")]).case_insensitive(true);
+        let map = config_with(options).unwrap();
+        let tokenizer = Tokenizer::with_options(&map["en"], MatchOptions {
+            preserve_case: true,
+            ..MatchOptions::default()
+        });
+
+        assert_eq!(tokenizer.tokenize("Main Street"), "Main St");
+        assert_eq!(tokenizer.tokenize("MAIN STREET"), "MAIN ST");
+        assert_eq!(tokenizer.tokenize("main street"), "main st");
+        assert_eq!(tokenizer.tokenize("Main Street Northwest"), "Main St NW");
+        assert_eq!(tokenizer.tokenize("main street northwest"), "main st nw");
+        assert_eq!(tokenizer.tokenize("Main StReEt"), "Main St");
+        assert_eq!(Tokenizer::new(&map["en"]).tokenize("MAIN STREET"), "MAIN St");
+
+        let expanding = Tokenizer::with_options(&map["en"], MatchOptions {
+            preserve_case: true,
+            direction: Direction::Expand,
+            ..MatchOptions::default()
+        });
+        assert_eq!(expanding.tokenize("MAIN AVE"), "MAIN AVENUE");
+        assert_eq!(expanding.tokenize("main ave"), "main avenue");
+    }
+
+    #[test]
+    fn test_dutch_title_case() {
+        let tokens = vec![token(r#"{ "tokens": ["laan", "ln"], "full": "laan", "canonical": "ln" }"#)];
+        let options = MatchOptions {
+            title_case_output: true,
+            ..MatchOptions::default()
+        };
+        let tokenizer = Tokenizer::for_language("nl", &tokens).with_match_options(options.clone());
+
+        assert_eq!(tokenizer.tokenize("van der berg straat"), "van der Berg Straat");
+        assert_eq!(tokenizer.tokenize("Van Der Berg laan"), "van der Berg Ln");
+        assert_eq!(Tokenizer::with_options(&tokens, options.clone()).tokenize("van der berg straat"), "Van Der Berg Straat");
+
+        let mut map = HashMap::new();
+        map.insert(String::from("nl"), tokens);
+        let tokenizer = Tokenizer::from_config(&map, &["nl"]).unwrap().with_match_options(options);
+        assert_eq!(tokenizer.tokenize("van der berg laan"), "van der Berg Ln");
+    }
+
+    #[test]
+    fn test_skip_urls_emails() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let text = "Main Street, info@Main-Street.com, https://Main-Street.com/Street";
+        let tokenizer = Tokenizer::with_options(&map["en"], MatchOptions {
+            skip_urls_emails: true,
+            ..MatchOptions::default()
+        });
+
+        assert_eq!(tokenizer.tokenize(text), "Main St, info@Main-Street.com, https://Main-Street.com/Street");
+        assert_eq!(Tokenizer::new(&map["en"]).tokenize(text), "Main St, info@Main-St.com, https://Main-St.com/St");
+    }
+
+    #[test]
+    fn test_protected_phrases() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let tokenizer = Tokenizer::with_options(&map["en"], MatchOptions {
+            protected_phrases: vec![String::from("Kings Highway")],
+            ..MatchOptions::default()
+        });
+
+        assert_eq!(tokenizer.tokenize("12 Kings Highway"), "12 Kings Highway");
+        assert_eq!(tokenizer.tokenize("Kings Highway, Old Highway"), "Kings Highway, Old Hwy");
+        assert_eq!(Tokenizer::new(&map["en"]).tokenize("Old Highway"), "Old Hwy");
+    }
+
+    #[test]
+    fn test_ascii_canonical() {
+        let tokens = vec![token(r#"{ "tokens": ["Boulevard", "Bd", "Bd\u00e9", "Boul\u00e9vard"], "full": "Boul\u00e9vard", "canonical": "Bd\u00e9", "asciiCanonical": "Bde" }"#)];
+        let tokenizer = Tokenizer::new(&tokens);
+
+        assert_eq!(tokenizer.tokenize("Boulévard Haussmann"), "Bdé Haussmann");
+        assert_eq!(tokenizer.tokenize("Boulevard Haussmann"), "Bde Haussmann");
+
+        let tokens = vec![token(r#"{ "tokens": ["St", "Street"], "full": "Street", "canonical": "St" }"#)];
+        assert_eq!(tokens[0].ascii_canonical, None);
+        assert_eq!(Tokenizer::new(&tokens).tokenize("Main Street"), "Main St");
+    }
+
+    #[test]
+    fn test_strip_control() {
+        let tokens = vec![token(r#"{ "tokens": ["PO Box", "Post Office Box"], "full": "Post Office Box", "canonical": "PO Box", "spanBoundaries": 2 }"#)];
+        let text = "Post\u{200B}\u{00A0}Office Box 12";
+        let tokenizer = Tokenizer::with_options(&tokens, MatchOptions {
+            normalize: NormalizeOptions {
+                strip_control: true,
+                ..NormalizeOptions::default()
+            },
+            ..MatchOptions::default()
+        });
+
+        assert_eq!(tokenizer.tokenize(text), "PO Box 12");
+        assert_eq!(tokenizer.tokenize_cow(text), "PO Box 12");
+        assert_eq!(Tokenizer::new(&tokens).tokenize(text), text);
+        assert_eq!(Tokenizer::new(&tokens).tokenize("Post\u{00A0}Office Box"), "PO Box");
+    }
+
+    #[test]
+    fn test_span_boundaries() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let tokenizer = Tokenizer::new(&map["en"]);
+        assert_eq!(tokenizer.tokenize("Village Post Office"), "Vpo");
+        assert_eq!(tokenizer.tokenize("Main Post  Office"), "Main Po");
+
+        // the same forms, allowed one boundary fewer
+        let tokens = vec![
+            token(r#"{ "tokens": ["Vpo", "Village Post Office"], "full": "Village Post Office", "canonical": "Vpo", "spanBoundaries": 1 }"#),
+            token(r#"{ "tokens": ["Po", "Post Office"], "full": "Post Office", "canonical": "Po" }"#),
+        ];
+        let tokenizer = Tokenizer::new(&tokens);
+        assert_eq!(tokenizer.tokenize("Village Post Office"), "Village Post Office");
+        assert_eq!(tokenizer.tokenize("Main Post Office"), "Main Post Office");
+        assert_eq!(tokenizer.tokenize("Po Box"), "Po Box");
+
+        // Multi-word forms need `spanBoundaries`, which is why the multi-word fixtures of other
+        // tests set it.
+        let tokens = vec![token(r#"{ "tokens": ["PO Box", "Post Office Box"], "full": "Post Office Box", "canonical": "PO Box" }"#)];
+        assert_eq!(Tokenizer::new(&tokens).tokenize("Post Office Box 12"), "Post Office Box 12");
+        let tokens = vec![token(r#"{ "tokens": ["PO Box", "Post Office Box"], "full": "Post Office Box", "canonical": "PO Box", "spanBoundaries": 2 }"#)];
+        assert_eq!(Tokenizer::new(&tokens).tokenize("Post Office Box 12"), "PO Box 12");
+    }
+
+    #[test]
+    fn test_diacritic_stripping() {
+        let map = config(vec![String::from("es")]).unwrap();
+        let es = Tokenizer::for_language("es", &map["es"]);
+        assert_eq!(es.tokenize("Autovía del Sur"), "Autov del Sur");
+        assert_eq!(es.tokenize("Autovia del Sur"), "Autov del Sur");
+        assert_eq!(es.tokenize("Autovi\u{0301}a del Sur"), "Autov del Sur");
+
+        let tokens = vec![
+            token(r#"{ "tokens": ["Dna", "Dona"], "full": "Dona", "canonical": "Dna" }"#),
+            token(r#"{ "tokens": ["Pl", "Plaza"], "full": "Plaza", "canonical": "Pl", "skipDiacriticStripping": true }"#),
+        ];
+        let tokenizer = Tokenizer::new(&tokens);
+        assert_eq!(tokenizer.tokenize("Doña Plaza"), "Dna Pl");
+        assert_eq!(tokenizer.tokenize("Dona Plazá"), "Dna Plazá");
+    }
+
+    #[test]
+    fn test_unicode_boundaries() {
+        let tokens = vec![
+            token(r#"{ "tokens": ["Str", "Straße"], "full": "Straße", "canonical": "Str" }"#),
+            token(r#"{ "tokens": ["Av", "Avenue"], "full": "Avenue", "canonical": "Av" }"#),
+        ];
+        let tokenizer = Tokenizer::new(&tokens);
+
+        assert_eq!(tokenizer.tokenize("Großstraße"), "Großstraße");
+        assert_eq!(tokenizer.tokenize("Müller Straße"), "Müller Str");
+        assert_eq!(tokenizer.tokenize("éStraße"), "éStraße");
+        // Diacritics are ignored, so the combining mark counts as part of the match.
+        assert_eq!(tokenizer.tokenize("Straße\u{0301}"), "Str");
+        assert_eq!(tokenizer.tokenize("l'Avenue"), "l'Av");
+    }
+
+    #[test]
+    fn test_unicode_boundaries_skip_diacritic_stripping() {
+        let tokens = vec![token(r#"{ "tokens": ["Str", "Straße"], "full": "Straße", "canonical": "Str", "skipDiacriticStripping": true }"#)];
+        let tokenizer = Tokenizer::new(&tokens);
+
+        assert_eq!(tokenizer.tokenize("Müller Straße"), "Müller Str");
+        assert_eq!(tokenizer.tokenize("Straße\u{0301}"), "Straße\u{0301}");
+    }
+
+    #[test]
+    fn test_skip_boundaries() {
+        let anchored = vec![token(r#"{ "tokens": ["st", "street"], "full": "street", "canonical": "st" }"#)];
+        assert_eq!(abbreviate("main street", &anchored), "main st");
+        assert_eq!(abbreviate("fast street", &anchored), "fast st");
+        assert_eq!(abbreviate("faststreet", &anchored), "faststreet");
+
+        let anywhere = vec![token(r#"{ "tokens": ["st", "street"], "full": "street", "canonical": "st", "skipBoundaries": true }"#)];
+        assert_eq!(abbreviate("faststreet", &anywhere), "fastst");
+        assert_eq!(expand("main st", &anywhere), "main street");
+        assert_eq!(expand("fast", &anywhere), "fastreet");
+        assert_eq!(expand("fast", &anchored), "fast");
+    }
+
+    #[test]
+    fn test_requires_number() {
+        let tokens = vec![token(r#"{ "tokens": ["Apt", "Apartment"], "full": "Apartment", "canonical": "Apt", "requiresNumber": true }"#)];
+        let tokenizer = Tokenizer::new(&tokens);
+
+        assert_eq!(tokenizer.tokenize("Apartment 4"), "Apt 4");
+        assert_eq!(tokenizer.tokenize("4 Apartment"), "4 Apt");
+        assert_eq!(tokenizer.tokenize("Sunny Apartment"), "Sunny Apartment");
+        assert_eq!(tokenizer.tokenize("Apartment"), "Apartment");
+    }
+
+    #[test]
+    fn test_abbreviate_with_spans() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let input = "Río North Main Street";
+        let (output, replacements) = abbreviate_with_spans(input, &map["en"]);
+
+        assert_eq!(output, abbreviate(input, &map["en"]));
+        assert_eq!(output, "Río N Main St");
+        assert_eq!(replacements.len(), 2);
+        assert_eq!((replacements[0].start, replacements[0].end), (5, 10));
+        assert_eq!(replacements[0].matched, "North");
+        assert_eq!(replacements[0].replacement, "N");
+        assert_eq!(replacements[1].matched, &input[replacements[1].start..replacements[1].end]);
+        assert_eq!(replacements[1].matched, "Street");
+        assert_eq!(replacements[1].replacement, "St");
+        assert_eq!(map["en"][replacements[1].token_index].full.as_str(), "Street");
+    }
+
+    #[test]
+    fn test_abbreviate_batch() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let inputs: Vec<String> = ["Main Street", "North Park Avenue", "", "Post Office Box 12", "nothing"]
+            .iter()
+            .cycle()
+            .take(50)
+            .map(|s| s.to_string())
+            .collect();
+        let single: Vec<String> = inputs.iter().map(|input| abbreviate(input, &map["en"])).collect();
+
+        assert_eq!(abbreviate_batch(&inputs, &map["en"]), single);
+        #[cfg(feature = "parallel")]
+        {
+            assert_eq!(abbreviate_batch_par(&inputs, &map["en"]), single);
+            assert!(abbreviate_batch_par(&[], &map["en"]).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_tokenize_with() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let tokenizer = Tokenizer::new(&map["en"]);
+
+        assert_eq!(tokenizer.tokenize_with("12 Main Street", |tk, _| tk.canonical.to_uppercase()), "12 Main ST");
+        assert_eq!(tokenizer.tokenize_with("Main Street", |_, matched| format!("<{}>", matched)), "Main <Street>");
+        assert_eq!(tokenizer.tokenize_with("nothing here", |_, _| unreachable!()), "nothing here");
+    }
+
+    #[test]
+    fn test_skip_numeric_spans() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let options = MatchOptions {
+            direction: Direction::Expand,
+            ..MatchOptions::default()
+        };
+        let skipping = Tokenizer::with_options(&map["en"], MatchOptions {
+            skip_numeric_spans: true,
+            ..options.clone()
+        });
+
+        assert_eq!(Tokenizer::with_options(&map["en"], options).tokenize("Main Ave, 40.12, -74.1"), "Main Avenue, 40.twelve, -74.one");
+        assert_eq!(skipping.tokenize("Main Ave, 40.12, -74.1"), "Main Avenue, 40.12, -74.1");
+        assert_eq!(skipping.tokenize("1 Main Ave on 2024-01-15"), "one Main Avenue on 2024-01-15");
+        assert_eq!(numeric_spans("at 12:30, 1.5- 3 km"), vec![(3, 8), (10, 13)]);
+    }
+
+    #[test]
+    fn test_last_word_only() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let tokenizer = Tokenizer::with_options(&map["en"], MatchOptions {
+            last_word_only: true,
+            ..MatchOptions::default()
+        });
+
+        assert_eq!(tokenizer.tokenize("Main Street"), "Main St");
+        assert_eq!(tokenizer.tokenize("Main Street."), "Main St.");
+        assert_eq!(tokenizer.tokenize("Street Fighter"), "Street Fighter");
+        assert_eq!(tokenizer.tokenize("North Main Street"), "North Main St");
+    }
+
+    #[test]
+    fn test_with_token_languages() {
+        let map = config(vec![String::from("en"), String::from("de")]).unwrap();
+        let merged = crate::merge(map, &[String::from("en"), String::from("de")]);
+        let tokenizer = Tokenizer::with_token_languages(&merged);
+
+        assert_eq!(tokenizer.tokenize("Berliner Strasse"), "Berliner Str");
+        assert_eq!(tokenizer.tokenize("Main Street"), "Main St");
+        let (lc, tk) = tokenizer.find_with_language("Straße").unwrap();
+        assert_eq!((lc, tk.language.as_deref()), ("de", Some("de")));
+    }
+
+    #[test]
+    fn test_tokenize_diagnostic() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let tokenizer = Tokenizer::new(&map["en"]);
+        let full = |d: &Decision| tokenizer.tokens()[d.candidate.token_index].full.as_str().to_string();
+
+        let diagnostics = tokenizer.tokenize_diagnostic("Main Streetside");
+        assert_eq!(diagnostics.output, "Main Streetside");
+        assert!(diagnostics.decisions.iter().any(|d| full(d) == "Street" && d.rejection == Some(Rejection::Boundary)));
+        assert!(diagnostics.decisions.iter().all(|d| d.rejection.is_some()));
+
+        let diagnostics = tokenizer.tokenize_diagnostic("Main Street");
+        assert_eq!(diagnostics.output, tokenizer.tokenize("Main Street"));
+        let applied: Vec<&Decision> = diagnostics.decisions.iter().filter(|d| d.rejection.is_none()).collect();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(full(applied[0]), "Street");
+
+        let limited = Tokenizer::with_options(&map["en"], MatchOptions {
+            max_replacements: Some(1),
+            ..MatchOptions::default()
+        });
+        let diagnostics = limited.tokenize_diagnostic("Main Street Apartment");
+        assert!(diagnostics.decisions.iter().any(|d| d.rejection == Some(Rejection::MaxReplacements)));
+    }
+
+    #[test]
+    fn test_tokenize_diagnostic_near_misses() {
+        let tokens = vec![
+            token(r#"{ "tokens": ["St", "Street"], "full": "Street", "canonical": "St" }"#),
+            token(r#"{ "tokens": ["Pl", "Plaça"], "full": "Plaça", "canonical": "Pl", "skipDiacriticStripping": true }"#),
+        ];
+        let tokenizer = Tokenizer::new(&tokens);
+        let rejections = |text: &str| -> Vec<(String, Option<Rejection>)> {
+            tokenizer.tokenize_diagnostic(text).decisions.into_iter()
+                .map(|d| (text[d.candidate.start..d.candidate.end].to_string(), d.rejection))
+                .collect()
+        };
+
+        assert_eq!(rejections("Main STREET"), vec![(String::from("STREET"), Some(Rejection::CaseSensitive))]);
+        assert!(rejections("Placa Major").contains(&(String::from("Placa"), Some(Rejection::Diacritics))));
+        assert!(rejections("Plaça Major").contains(&(String::from("Plaça"), None)));
+        assert!(rejections("Plaça Major").iter().all(|(_, rejection)| rejection != &Some(Rejection::Diacritics)));
+        assert!(rejections("Main Streetside").iter().all(|(_, rejection)| rejection == &Some(Rejection::Boundary)));
+        assert!(rejections("Main STREETSIDE").is_empty());
+        assert_eq!(tokenizer.tokenize_diagnostic("Main STREET").output, "Main STREET");
+    }
+
+    #[test]
+    fn test_changing_languages() {
+        let map = config(Vec::new()).unwrap();
+        assert_eq!(changing_languages("Berliner Straße 12", &map), vec![String::from("de")]);
+        assert!(changing_languages("12", &map).is_empty());
+    }
+
+    #[test]
+    fn test_global_tokens() {
+        let options = ConfigOptions::new().languages(vec![String::from("en")]).include_global(true);
+        let map = config_with(options.build()).unwrap();
+        assert_eq!(Tokenizer::new(&map["en"]).tokenize("Apt № 5 Main Street"), "Apt # 5 Main St");
+    }
+
+    #[test]
+    fn test_newline_as_space() {
+        let tokens = vec![token(r#"{ "tokens": ["PO Box", "Post Office Box"], "full": "Post Office Box", "canonical": "PO Box", "spanBoundaries": 2 }"#)];
+        let tokenizer = Tokenizer::with_options(&tokens, MatchOptions {
+            normalize: NormalizeOptions {
+                newline_as_space: true,
+                ..NormalizeOptions::default()
+            },
+            ..MatchOptions::default()
+        });
+
+        assert_eq!(tokenizer.tokenize("Post\r\nOffice Box 12\nSpringfield"), "PO Box 12 Springfield");
+        assert_eq!(Tokenizer::new(&tokens).tokenize("Post\nOffice Box 12"), "PO Box 12");
+    }
+
+    #[test]
+    fn test_length_constraints() {
+        let unbounded = vec![token(r#"{ "tokens": ["S", "Street"], "full": "Street", "canonical": "St" }"#)];
+        assert_eq!(Tokenizer::new(&unbounded).tokenize("Main S"), "Main St");
+
+        let bounded = vec![token(r#"{ "tokens": ["S", "Street"], "full": "Street", "canonical": "St", "minLength": 2 }"#)];
+        let tokenizer = Tokenizer::new(&bounded);
+        assert_eq!(tokenizer.tokenize("Main S"), "Main S");
+        assert_eq!(tokenizer.tokenize("Main Street"), "Main St");
+
+        let capped = vec![token(r#"{ "tokens": ["S", "Street"], "full": "Street", "canonical": "St", "maxLength": 1 }"#)];
+        assert_eq!(Tokenizer::new(&capped).tokenize("Main Street"), "Main Street");
+    }
+
+    #[test]
+    fn test_named_groups() {
+        let tokens = vec![token(r#"{ "tokens": ["", "(?P<num>\\d+)(?:\\s*)(?<suffix>st|nd|rd|th)\\b"], "full": "(?P<num>\\d+)(?:\\s*)(?<suffix>st|nd|rd|th)\\b", "canonical": "${num}${suffix}", "regex": true, "spanBoundaries": 1 }"#)];
+        let tokenizer = Tokenizer::new(&tokens);
+        assert_eq!(tokenizer.tokenize("21 st Avenue"), "21st Avenue");
+
+        let tokens = vec![token(r#"{ "tokens": ["", "(a)(?P<b>b)(?<=ab)"], "full": "(a)(?P<b>b)(?<=ab)", "canonical": "$b-$1-$missing", "regex": true }"#)];
+        assert_eq!(Tokenizer::new(&tokens).tokenize("ab"), "b-a-$missing");
+    }
+
+    #[test]
+    fn test_min_token_len() {
+        let tokens = vec![
+            token(r#"{ "tokens": ["St", "Street"], "full": "Street", "canonical": "St" }"#),
+            token(r#"{ "tokens": ["Rd", "Road"], "full": "Road", "canonical": "Rd", "minLength": 5 }"#),
+        ];
+        let tokenizer = Tokenizer::with_options(&tokens, MatchOptions {
+            direction: Direction::Expand,
+            min_token_len: 3,
+            ..MatchOptions::default()
+        });
+
+        assert_eq!(tokenizer.tokenize("Main St"), "Main St");
+        assert_eq!(Tokenizer::with_options(&tokens, MatchOptions {
+            direction: Direction::Expand,
+            ..MatchOptions::default()
+        }).tokenize("Main St"), "Main Street");
+        assert_eq!(tokenizer.tokenize("Main Rd"), "Main Rd");
+    }
+
+    #[test]
+    fn test_max_replacements() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let tokenizer = Tokenizer::with_options(&map["en"], MatchOptions {
+            max_replacements: Some(1),
+            ..MatchOptions::default()
+        });
+
+        assert_eq!(tokenizer.tokenize("Main Street Road"), "Main St Road");
+        assert_eq!(tokenizer.find_tokens("Main Street Road").len(), 1);
+        assert_eq!(Tokenizer::new(&map["en"]).tokenize("Main Street Road"), "Main St Rd");
+
+        // Scanning stops at the first match past the limit, however many follow it.
+        let text = "Main Street ".to_string() + &"Road ".repeat(100);
+        let decisions = tokenizer.tokenize_diagnostic(&text).decisions;
+        assert_eq!(decisions.iter().filter(|d| d.rejection.is_none()).count(), 1);
+        assert_eq!(decisions.last().unwrap().rejection, Some(Rejection::MaxReplacements));
+        assert!(decisions.len() < 10);
+        assert!(Tokenizer::new(&map["en"]).tokenize_diagnostic(&text).decisions.len() > 100);
+    }
+
+    #[test]
+    fn test_cjk_span_boundaries() {
+        let tokens = vec![token(r#"{ "tokens": ["", "(\\p{Han}+)马路"], "full": "(\\p{Han}+)马路", "canonical": "${1}路", "regex": true, "spanBoundaries": 3 }"#)];
+        let tokenizer = Tokenizer::new(&tokens);
+
+        assert_eq!(tokenizer.tokenize("中马路"), "中路");
+        assert_eq!(tokenizer.tokenize("中山马路"), "中山马路");
+    }
+
+    #[test]
+    fn test_grapheme_count() {
+        assert_eq!(grapheme_count("中山路"), 3);
+        assert_eq!(grapheme_count("e\u{0301}"), 1);
+        assert_eq!(grapheme_count("\u{1F468}\u{200D}\u{1F469}"), 1);
+    }
+
+    #[test]
+    fn test_interior_whitespace() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let tokenizer = Tokenizer::new(&map["en"]);
+
+        assert_eq!(tokenizer.tokenize("Post Office"), "Po");
+        assert_eq!(tokenizer.tokenize("Post  Office"), "Po");
+        assert_eq!(tokenizer.tokenize("Post\tOffice"), "Po");
+        assert_eq!(tokenizer.tokenize("PostOffice"), "PostOffice");
+    }
+
+    #[test]
+    fn test_canonical_separator() {
+        let tokens = vec![token(r#"{ "tokens": ["New York", "New York City"], "full": "New York City", "canonical": "New York", "spanBoundaries": 2 }"#)];
+        assert_eq!(Tokenizer::new(&tokens).tokenize("New York City"), "New York");
+
+        let options = MatchOptions {
+            canonical_separator: Some('_'),
+            ..MatchOptions::default()
+        };
+        let tokenizer = Tokenizer::with_options(&tokens, options);
+        assert_eq!(tokenizer.tokenize("New York City"), "New_York");
+        assert_eq!(tokenizer.tokenize("Downtown New York City"), "Downtown New_York");
+    }
+
+    #[test]
+    fn test_sharp_s() {
+        let tokens = vec![token(r#"{ "tokens": ["Hauptstr", "Hauptstraße"], "full": "Hauptstraße", "canonical": "Hauptstr" }"#)];
+        let tokenizer = Tokenizer::for_language("de", &tokens);
+        let strasse = tokenizer.find_tokens("Hauptstrasse 1");
+        let strasze = tokenizer.find_tokens("Hauptstraße 1");
+        assert_eq!(strasse.len(), 1);
+        assert_eq!(strasze.len(), 1);
+        assert_eq!(strasse[0].token_index, strasze[0].token_index);
+        assert_eq!(tokenizer.tokenize("Hauptstraße 1"), "Hauptstr 1");
+        assert_eq!(tokenizer.tokenize("Hauptstrasse 1"), "Hauptstr 1");
+        assert_eq!(Tokenizer::new(&tokens).tokenize("Hauptstrasse 1"), "Hauptstrasse 1");
+
+        // Options set after choosing the language keep the German equivalence.
+        let options = MatchOptions {
+            max_replacements: Some(1),
+            ..MatchOptions::default()
+        };
+        let limited = Tokenizer::for_language("de", &tokens).with_match_options(options.clone());
+        assert_eq!(limited.tokenize("Hauptstrasse 1, Hauptstraße 2"), "Hauptstr 1, Hauptstraße 2");
+        let mut map = HashMap::new();
+        map.insert(String::from("de"), tokens);
+        let limited = Tokenizer::from_config(&map, &["de"]).unwrap().with_match_options(options);
+        assert_eq!(limited.tokenize("Hauptstrasse 1, Hauptstraße 2"), "Hauptstr 1, Hauptstraße 2");
+
+        let tokens = vec![token(r#"{ "tokens": ["Gr", "Gross"], "full": "Gross", "canonical": "Gr" }"#)];
+        assert_eq!(Tokenizer::for_language("de", &tokens).tokenize("Groß"), "Gr");
+
+        let tokens = vec![token(r#"{ "tokens": ["Hauptstr", "Hauptstraße"], "full": "Hauptstraße", "canonical": "Hauptstr", "skipDiacriticStripping": true }"#)];
+        assert_eq!(Tokenizer::for_language("de", &tokens).tokenize("Hauptstrasse 1"), "Hauptstrasse 1");
+    }
+
+    #[test]
+    fn test_find_with_language() {
+        let map = config(vec![String::from("nl"), String::from("fr")]).unwrap();
+
+        let tokenizer = Tokenizer::from_config(&map, &["nl", "fr"]).unwrap();
+        let (lc, tk) = tokenizer.find_with_language("dijk").unwrap();
+        assert_eq!((lc, tk.canonical.as_str()), ("nl", "dk"));
+        let (lc, tk) = tokenizer.find_with_language("Boulevard").unwrap();
+        assert_eq!((lc, tk.canonical.as_str()), ("fr", "Bd"));
+        assert_eq!(tokenizer.find_with_language("Dr").unwrap().0, "nl");
+        assert!(tokenizer.find_with_language("dijk 1").is_none());
+
+        let tokenizer = Tokenizer::from_config(&map, &["fr", "nl"]).unwrap();
+        assert_eq!(tokenizer.find_with_language("Dr").unwrap().0, "fr");
+
+        assert!(Tokenizer::from_config(&map, &["de"]).is_err());
+        assert!(Tokenizer::new(&map["nl"]).find_with_language("dijk").is_none());
+    }
+
+    struct UppercaseMatcher;
+
+    impl Matcher for UppercaseMatcher {
+        fn find(&self, text: &str, context: &MatchContext) -> Vec<TokenMatch> {
+            let mut matches = Vec::new();
+            for (token_index, tk) in context.tokens.iter().enumerate() {
+                if let Replacer::String(full) = &tk.full {
+                    let upper = full.to_uppercase();
+                    for (start, _) in text.match_indices(&upper) {
+                        matches.push(TokenMatch::new(start, start + upper.len(), token_index, tk.canonical.to_uppercase()));
+                    }
+                }
+            }
+            matches
+        }
+    }
+
+    #[test]
+    fn test_custom_matcher() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let tokenizer = Tokenizer::new(&map["en"]);
+        assert_eq!(tokenizer.tokenize("MAIN STREET"), "MAIN STREET");
+
+        let tokenizer = tokenizer.with_matcher(UppercaseMatcher);
+        assert_eq!(tokenizer.tokenize("MAIN STREET"), "MAIN ST");
+        assert_eq!(tokenizer.tokenize("Main Street"), "Main Street");
+        assert_eq!(tokenizer.tokenize("STREETSBORO"), "STREETSBORO");
+    }
+
+    #[test]
+    fn test_extract_typed() {
+        let map = config(vec![String::from("en")]).unwrap();
+
+        let components = extract_typed("123 North Main Street", &map["en"]);
+        assert_eq!(components[&TokenType::Way], vec![String::from("Street")]);
+        assert_eq!(components[&TokenType::Cardinal], vec![String::from("North")]);
+        assert!(!components.contains_key(&TokenType::Number));
+
+        assert!(extract_typed("Main", &map["en"]).is_empty());
+    }
+
+    #[test]
+    fn test_match_token_type() {
+        let map = config(vec![String::from("en")]).unwrap();
+        let matches = Tokenizer::new(&map["en"]).find_tokens("Main Street");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].token_type, Some(TokenType::Way));
+        assert_eq!(matches[0].token_type, map["en"][matches[0].token_index].token_type);
+    }
+}
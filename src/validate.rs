@@ -0,0 +1,131 @@
+use crate::{Replacer, Token};
+
+// ISO 3166-1 alpha-2, officially assigned codes only.
+const COUNTRY_CODES: &[&str] = &[
+    "AD", "AE", "AF", "AG", "AI", "AL", "AM", "AO", "AQ", "AR", "AS", "AT", "AU", "AW", "AX", "AZ",
+    "BA", "BB", "BD", "BE", "BF", "BG", "BH", "BI", "BJ", "BL", "BM", "BN", "BO", "BQ", "BR", "BS",
+    "BT", "BV", "BW", "BY", "BZ", "CA", "CC", "CD", "CF", "CG", "CH", "CI", "CK", "CL", "CM", "CN",
+    "CO", "CR", "CU", "CV", "CW", "CX", "CY", "CZ", "DE", "DJ", "DK", "DM", "DO", "DZ", "EC", "EE",
+    "EG", "EH", "ER", "ES", "ET", "FI", "FJ", "FK", "FM", "FO", "FR", "GA", "GB", "GD", "GE", "GF",
+    "GG", "GH", "GI", "GL", "GM", "GN", "GP", "GQ", "GR", "GS", "GT", "GU", "GW", "GY", "HK", "HM",
+    "HN", "HR", "HT", "HU", "ID", "IE", "IL", "IM", "IN", "IO", "IQ", "IR", "IS", "IT", "JE", "JM",
+    "JO", "JP", "KE", "KG", "KH", "KI", "KM", "KN", "KP", "KR", "KW", "KY", "KZ", "LA", "LB", "LC",
+    "LI", "LK", "LR", "LS", "LT", "LU", "LV", "LY", "MA", "MC", "MD", "ME", "MF", "MG", "MH", "MK",
+    "ML", "MM", "MN", "MO", "MP", "MQ", "MR", "MS", "MT", "MU", "MV", "MW", "MX", "MY", "MZ", "NA",
+    "NC", "NE", "NF", "NG", "NI", "NL", "NO", "NP", "NR", "NU", "NZ", "OM", "PA", "PE", "PF", "PG",
+    "PH", "PK", "PL", "PM", "PN", "PR", "PS", "PT", "PW", "PY", "QA", "RE", "RO", "RS", "RU", "RW",
+    "SA", "SB", "SC", "SD", "SE", "SG", "SH", "SI", "SJ", "SK", "SL", "SM", "SN", "SO", "SR", "SS",
+    "ST", "SV", "SX", "SY", "SZ", "TC", "TD", "TF", "TG", "TH", "TJ", "TK", "TL", "TM", "TN", "TO",
+    "TR", "TT", "TV", "TW", "TZ", "UA", "UG", "UM", "US", "UY", "UZ", "VA", "VC", "VE", "VG", "VI",
+    "VN", "VU", "WF", "WS", "YE", "YT", "ZA", "ZM", "ZW",
+];
+
+/// Checks `tokens` for structural mistakes and reports all of them at once, each naming the
+/// offending token by its `full` form. Unlike `validation_warnings`, these are always errors
+/// in the token file. Unknown `onlyLayers` values already fail to load, see
+/// `Error::LayerNotSupported`.
+pub fn validate(tokens: &[Token]) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    for (i, tk) in tokens.iter().enumerate() {
+        let full = tk.full.as_str();
+        if tk.tokens.is_empty() {
+            errors.push(format!("{}: tokens is empty", full));
+        } else if !tk.regex && !tk.tokens.contains(&tk.canonical) {
+            errors.push(format!("{}: canonical \"{}\" not present in tokens", full, tk.canonical));
+        }
+        if tk.only_layers.as_ref().is_some_and(|layers| layers.is_empty()) {
+            errors.push(format!("{}: onlyLayers is empty", full));
+        }
+        if tokens[..i].iter().any(|other| other.full.as_str() == full && other.tokens == tk.tokens) {
+            errors.push(format!("{}: duplicate of an earlier group", full));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Flags flag combinations that parse fine but probably don't do what the author meant.
+/// Each warning names the offending token by its `full` form.
+pub fn validation_warnings(tokens: &[Token]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for tk in tokens {
+        let full = tk.full.as_str();
+        if let Replacer::String(full) = &tk.full {
+            if tk.skip_boundaries && full.trim().contains(char::is_whitespace) {
+                warnings.push(format!("{}: skipBoundaries on a multi-word token lets it match inside other words", full));
+            }
+        }
+        if tk.prefer_full && tk.regex {
+            warnings.push(format!("{}: preferFull has no effect on a regex token", full));
+        }
+        if tk.only_countries.as_ref().is_some_and(|countries| countries.is_empty()) {
+            warnings.push(format!("{}: empty onlyCountries restricts the token to no country at all", full));
+        }
+        for country in tk.only_countries.iter().flatten() {
+            if COUNTRY_CODES.binary_search(&country.to_uppercase().as_str()).is_err() {
+                warnings.push(format!("{}: onlyCountries entry \"{}\" is not an ISO 3166-1 alpha-2 code", full, country));
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::test_util::token;
+
+    #[test]
+    fn test_validation_warnings() {
+        let tokens = vec![
+            token(r#"{ "tokens": ["Po", "Post Office"], "full": "Post Office", "canonical": "Po", "skipBoundaries": true }"#),
+            token(r#"{ "tokens": ["", "a+"], "full": "a+", "canonical": "", "regex": true, "preferFull": true }"#),
+            token(r#"{ "tokens": ["St", "Street"], "full": "Street", "canonical": "St", "onlyCountries": [] }"#),
+            token(r#"{ "tokens": ["str", "strasse"], "full": "strasse", "canonical": "str", "skipBoundaries": true }"#),
+            token(r#"{ "tokens": ["Rd", "Road"], "full": "Road", "canonical": "Rd", "onlyCountries": ["us", "GB", "XX"] }"#),
+        ];
+        assert_eq!(validation_warnings(&tokens), vec![
+            String::from("Post Office: skipBoundaries on a multi-word token lets it match inside other words"),
+            String::from("a+: preferFull has no effect on a regex token"),
+            String::from("Street: empty onlyCountries restricts the token to no country at all"),
+            String::from("Road: onlyCountries entry \"XX\" is not an ISO 3166-1 alpha-2 code"),
+        ]);
+    }
+
+    #[test]
+    fn test_validate() {
+        let tokens = vec![
+            token(r#"{ "tokens": ["Street", "St"], "full": "Street", "canonical": "St" }"#),
+            token(r#"{ "tokens": ["Avenue"], "full": "Avenue", "canonical": "Ave" }"#),
+            token(r#"{ "tokens": [], "full": "Road", "canonical": "Rd" }"#),
+            token(r#"{ "tokens": ["Pl", "Place"], "full": "Place", "canonical": "Pl", "onlyLayers": [] }"#),
+            token(r#"{ "tokens": ["Street", "St"], "full": "Street", "canonical": "St", "type": "way" }"#),
+            token(r#"{ "tokens": ["", "(\\d+)th"], "full": "(\\d+)th", "canonical": "$1", "regex": true }"#),
+        ];
+        assert_eq!(validate(&tokens), Err(vec![
+            String::from("Avenue: canonical \"Ave\" not present in tokens"),
+            String::from("Road: tokens is empty"),
+            String::from("Place: onlyLayers is empty"),
+            String::from("Street: duplicate of an earlier group"),
+        ]));
+        assert_eq!(validate(&tokens[..1]), Ok(()));
+    }
+
+    #[test]
+    fn test_builtin_validate() {
+        for (lc, tokens) in config(Vec::new()).unwrap() {
+            assert_eq!(validate(&tokens), Ok(()), "{}", lc);
+        }
+    }
+
+    #[test]
+    fn test_builtin_validation_warnings() {
+        for (lc, tokens) in config(Vec::new()).unwrap() {
+            assert_eq!(validation_warnings(&tokens), Vec::<String>::new(), "{}", lc);
+        }
+    }
+}
@@ -31,7 +31,7 @@ tape((t) => {
         t.error(err);
 
         files.forEach((file) => {
-            if (!file.match(/\.json/) || file.match(/global/)) return;
+            if (!file.match(/\.json/) || file.match(/global/) || file.match(/\.[A-Z]{2}\.json$/)) return;
 
             let lang = path.basename(file, '.json');
             let tokens = config(lang);
@@ -48,7 +48,7 @@ tape((t) => {
         t.error(err);
 
         files.forEach((file) => {
-            if (!file.match(/\.json/) || file.match(/global/)) return;
+            if (!file.match(/\.json/) || file.match(/global/) || file.match(/\.[A-Z]{2}\.json$/)) return;
 
             let lang = path.basename(file, '.json');
             let tokens = config(lang, true, true);
@@ -61,13 +61,17 @@ tape((t) => {
                 full: { type: 'string', required: true },
                 canonical: { type: 'string', required: true },
 
+                asciiCanonical: { type: 'string', required: false },
+                maxLength: { type: 'number', required: false },
+                minLength: { type: 'number', required: false },
                 note: { type: 'string', required: false },
                 onlyCountries: { type: 'array', required: false },
-                onlyLayers: { type: 'array', required: false, allowed: [ 'address' ] },
+                onlyLayers: { type: 'array', required: false, allowed: [ 'country', 'region', 'postcode', 'district', 'place', 'locality', 'neighborhood', 'address', 'poi' ] },
                 onlyUseWhile: { type: 'array', required: false, allowed: [ 'processing', 'indexing', 'querying' ] },
                 preferFull: { type: 'boolean', required: false },
                 regex: { type: 'boolean', required: false },
                 reduceRelevance: { type: 'boolean', required: false },
+                requiresNumber: { type: 'boolean', required: false },
                 skipBoundaries: { type: 'boolean', required: false },
                 skipDiacriticStripping: { type: 'boolean', required: false },
                 spanBoundaries: { type: 'number', required: false },
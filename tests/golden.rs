@@ -0,0 +1,31 @@
+use geocoder_abbreviations::{abbreviate, config};
+use std::fs;
+
+// Each fixture line reads `input => expected`; blank lines and lines starting with `#` are
+// skipped.
+fn check_fixture(lc: &str) {
+    let path = format!("{}/tests/fixtures/{}.txt", env!("CARGO_MANIFEST_DIR"), lc);
+    let fixture = fs::read_to_string(&path).unwrap_or_else(|_| panic!("missing fixture {}", path));
+    let map = config(vec![lc.to_string()]).unwrap();
+
+    let mut failures = Vec::new();
+    for line in fixture.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let (input, expected) = line.split_once(" => ")
+            .unwrap_or_else(|| panic!("{}: malformed fixture line {:?}", lc, line));
+        let actual = abbreviate(input, &map[lc]);
+        if actual != expected {
+            failures.push(format!("{}: {:?} => expected {:?}, got {:?}", lc, input, expected, actual));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_en_fixture() {
+    check_fixture("en");
+}
+
+#[test]
+fn test_de_fixture() {
+    check_fixture("de");
+}
@@ -0,0 +1,33 @@
+// Dropping a regex token that doesn't compile must only show up in the report, never on
+// stdout. The harness captures a test's output, so the test runs itself again as a child with
+// capture off and checks that nothing was printed between two markers.
+
+use geocoder_abbreviations::{prepare_report, register_language};
+use std::process::Command;
+
+const CHILD_VAR: &str = "GEOCODER_ABBR_QUIET_CHILD";
+
+#[test]
+fn test_prepare_report_prints_nothing() {
+    if std::env::var_os(CHILD_VAR).is_some() {
+        register_language("zq", r##"[
+            { "tokens": ["Street", "St"], "full": "Street", "canonical": "St" },
+            { "tokens": ["", "(?<=\\d+ )Apt"], "full": "(?<=\\d+ )Apt", "canonical": "#", "regex": true }
+        ]"##);
+        print!("[begin]");
+        let report = prepare_report(vec![String::from("zq")]).unwrap();
+        print!("[end]");
+        assert_eq!(report.tokens["zq"].len(), 1);
+        assert_eq!(report.filtered["zq"], vec![String::from("(?<=\\d+ )Apt")]);
+        return;
+    }
+
+    let out = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "test_prepare_report_prints_nothing", "--nocapture", "--test-threads=1"])
+        .env(CHILD_VAR, "1")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success(), "{}{}", stdout, String::from_utf8_lossy(&out.stderr));
+    assert!(stdout.contains("[begin][end]"), "{}", stdout);
+}
@@ -0,0 +1,21 @@
+// Without `embedded-tokens`, `config` reads the token files from `GEOCODER_ABBR_DIR`. This is
+// the only test in its binary, so setting the variable can't race with another test reading it.
+#![cfg(not(feature = "embedded-tokens"))]
+
+use geocoder_abbreviations::{abbreviate, config, Error};
+use std::fs;
+
+#[test]
+fn test_config_from_dir() {
+    let dir = std::env::temp_dir().join(format!("geocoder-abbreviations-tokens-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("zr.json"), r#"[{ "tokens": ["Gt", "Gate"], "full": "Gate", "canonical": "Gt" }]"#).unwrap();
+    std::env::set_var("GEOCODER_ABBR_DIR", &dir);
+
+    let map = config(Vec::new()).unwrap();
+    assert_eq!(map.keys().collect::<Vec<_>>(), vec!["zr"]);
+    assert_eq!(abbreviate("Kings Gate", &map["zr"]), "Kings Gt");
+    assert_eq!(config(vec![String::from("en")]).unwrap_err(), Error::LanguageCodeNotSupported(String::from("en")));
+
+    fs::remove_dir_all(&dir).unwrap();
+}